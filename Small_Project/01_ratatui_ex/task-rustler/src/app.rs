@@ -1,6 +1,11 @@
+use crate::date::due_instant;
+use crate::error::Result;
+use crate::project::Project;
 use crate::task::Task;
 use crate::task_manager::{SortOrder, TasksService};
 use ratatui::widgets::ListState;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 
 pub struct TaskList {
     pub items: Vec<Task>,
@@ -21,12 +26,19 @@ pub enum InputMode {
     Adding,
     EditingExisting,
     Export,
+    Import,
+    NewProject,
+    MoveTask,
+    /// Completing a task with open subtasks, waiting to know if they're done too
+    ConfirmCompleteSubtasks,
 }
 #[derive(Debug)]
 pub enum InputFieldType {
     Title,
     Description,
     Date,
+    Tags,
+    Recurrence,
 }
 
 pub struct AppContext {
@@ -34,30 +46,64 @@ pub struct AppContext {
     pub input_title: String,
     pub input_description: String,
     pub input_date: String,
+    /// Comma separated tag names
+    pub input_tags: String,
+    /// Recurrence rule as typed, see `Recurrence`
+    pub input_recurrence: String,
     pub input_export_path: String,
+    pub input_import_path: String,
+    pub input_project_name: String,
     pub input_mode: InputMode,
     pub input_field: InputFieldType,
     pub tasks_service: TasksService,
     pub show_help: bool,
     pub error: Option<String>,
     pub sort_order: SortOrder,
+    /// Only tasks with this tag are listed
+    pub tag_filter: Option<String>,
+    /// Every project with its number of tasks, as shown in the sidebar
+    pub projects: Vec<(Project, usize)>,
+    /// Only tasks of this project are listed, all of them when `None`
+    pub project_filter: Option<i32>,
+    /// Index in `projects` of the destination picked in `InputMode::MoveTask`
+    pub move_target: usize,
+    /// Tasks whose subtasks are hidden
+    pub collapsed: HashSet<i32>,
+    /// Nesting level of every listed task, 0 for the top level
+    pub depths: HashMap<i32, usize>,
+    /// Completed and total number of direct subtasks, by parent id
+    pub subtask_counts: HashMap<i32, (usize, usize)>,
+    /// Parent of the task being added, `None` for a top level task
+    pub new_task_parent: Option<i32>,
 }
 
 impl AppContext {
-    pub fn new(db_path: String) -> AppContext {
-        AppContext {
+    pub fn new(db_path: String) -> Result<AppContext> {
+        Ok(AppContext {
             task_list: TaskList::new(),
             input_title: String::new(),
             input_description: String::new(),
             input_date: String::new(),
+            input_tags: String::new(),
+            input_recurrence: String::new(),
             input_export_path: String::new(),
+            input_import_path: String::new(),
+            input_project_name: String::new(),
             input_mode: InputMode::View,
             input_field: InputFieldType::Title,
-            tasks_service: TasksService::new(db_path),
+            tasks_service: TasksService::new(db_path)?,
             show_help: false,
             error: None,
-            sort_order: SortOrder::High
-        }
+            sort_order: SortOrder::High,
+            tag_filter: None,
+            projects: Vec::new(),
+            project_filter: None,
+            move_target: 0,
+            collapsed: HashSet::new(),
+            depths: HashMap::new(),
+            subtask_counts: HashMap::new(),
+            new_task_parent: None,
+        })
     }
 
     pub fn sort_by_priority(&mut self) {
@@ -70,18 +116,16 @@ impl AppContext {
                 .items
                 .sort_by(|a, b| a.priority.cmp(&b.priority))
         }
+        self.arrange_as_tree();
     }
 
     pub fn sort_by_date(&mut self) {
         self.cycle_sort_order();
-        match self.sort_order {
-            SortOrder::High => self.task_list
-                .items
-                .sort_by(|a, b| b.date.cmp(&a.date)),
-            SortOrder::Low => self.task_list
-                .items
-                .sort_by(|a, b| a.date.cmp(&b.date))
-        }
+        let order = self.sort_order;
+        self.task_list
+            .items
+            .sort_by(|a, b| cmp_dates_none_last(a, b, order));
+        self.arrange_as_tree();
     }
 
     pub fn select_none(&mut self) {
@@ -104,15 +148,103 @@ impl AppContext {
         self.task_list.state.select_last();
     }
 
-    pub fn refresh_task_list(&mut self) {
-        self.task_list.items = self.tasks_service.get_all_tasks_sorted_by_priority(self.sort_order);
+    pub fn refresh_task_list(&mut self) -> Result<()> {
+        self.projects = self.tasks_service.projects()?;
+        self.subtask_counts = self.tasks_service.subtask_counts()?;
+        self.task_list.items = match &self.tag_filter {
+            Some(tag) => {
+                let mut tasks = self.tasks_service.tasks_with_tag(tag)?;
+                if let SortOrder::Low = self.sort_order {
+                    // Same order as `get_all_tasks_sorted_by_priority`, lowest first
+                    tasks.sort_by(|a, b| b.priority.cmp(&a.priority));
+                }
+                tasks
+            }
+            None => self.tasks_service.get_all_tasks_sorted_by_priority(self.sort_order)?,
+        };
+        if let Some(project_id) = self.project_filter {
+            self.task_list.items.retain(|t| t.project_id == project_id);
+        }
+        self.arrange_as_tree();
+        Ok(())
+    }
+
+    /// Put every subtask right after its parent, keeping the current order between
+    /// siblings, and drop the subtasks of collapsed tasks. A task whose parent isn't
+    /// listed is shown at the top level.
+    fn arrange_as_tree(&mut self) {
+        let tasks = std::mem::take(&mut self.task_list.items);
+        let ids: HashSet<i32> = tasks.iter().map(|t| t.id).collect();
+        let mut roots = Vec::new();
+        let mut children: HashMap<i32, Vec<Task>> = HashMap::new();
+        for task in tasks {
+            match task.parent_id.filter(|id| ids.contains(id)) {
+                Some(parent_id) => children.entry(parent_id).or_default().push(task),
+                None => roots.push(task),
+            }
+        }
+        self.depths.clear();
+        let mut stack: Vec<(Task, usize)> = roots.into_iter().rev().map(|t| (t, 0)).collect();
+        while let Some((task, depth)) = stack.pop() {
+            if let Some(subtasks) = children.remove(&task.id) {
+                if !self.collapsed.contains(&task.id) {
+                    stack.extend(subtasks.into_iter().rev().map(|t| (t, depth + 1)));
+                }
+            }
+            self.depths.insert(task.id, depth);
+            self.task_list.items.push(task);
+        }
+    }
+
+    /// Id of the selected task, if any
+    pub fn selected_task_id(&self) -> Option<i32> {
+        let index = self.task_list.state.selected()?;
+        self.task_list.items.get(index).map(|t| t.id)
+    }
+
+    /// The project being viewed, `None` when all the tasks are listed
+    pub fn current_project(&self) -> Option<&Project> {
+        let id = self.project_filter?;
+        self.projects.iter().map(|(p, _)| p).find(|p| p.id == id)
+    }
+
+    /// View the next project, `step` is 1 or -1. "All" sits before the first project.
+    pub fn cycle_project(&mut self, step: isize) {
+        let len = self.projects.len() as isize + 1;
+        let current = match self.project_filter {
+            None => 0,
+            Some(id) => self
+                .projects
+                .iter()
+                .position(|(p, _)| p.id == id)
+                .map_or(0, |i| i as isize + 1),
+        };
+        let next = (current + step).rem_euclid(len);
+        self.project_filter = match next {
+            0 => None,
+            i => Some(self.projects[i as usize - 1].0.id),
+        };
+    }
+
+    pub fn next_move_target(&mut self) {
+        if !self.projects.is_empty() {
+            self.move_target = (self.move_target + 1) % self.projects.len();
+        }
+    }
+
+    pub fn previous_move_target(&mut self) {
+        if !self.projects.is_empty() {
+            self.move_target = (self.move_target + self.projects.len() - 1) % self.projects.len();
+        }
     }
 
     pub fn next_input_field(&mut self) {
         self.input_field = match self.input_field {
             InputFieldType::Title => InputFieldType::Description,
             InputFieldType::Description => InputFieldType::Date,
-            InputFieldType::Date => InputFieldType::Title,
+            InputFieldType::Date => InputFieldType::Tags,
+            InputFieldType::Tags => InputFieldType::Recurrence,
+            InputFieldType::Recurrence => InputFieldType::Title,
         }
     }
 
@@ -134,6 +266,12 @@ impl AppContext {
             InputFieldType::Date => {
                 self.input_date.pop();
             }
+            InputFieldType::Tags => {
+                self.input_tags.pop();
+            }
+            InputFieldType::Recurrence => {
+                self.input_recurrence.pop();
+            }
         }
     }
 
@@ -142,6 +280,23 @@ impl AppContext {
             InputFieldType::Title => self.input_title.push(c),
             InputFieldType::Description => self.input_description.push(c),
             InputFieldType::Date => self.input_date.push(c),
+            InputFieldType::Tags => self.input_tags.push(c),
+            InputFieldType::Recurrence => self.input_recurrence.push(c),
         }
     }
 }
+
+/// Newest (`High`) or oldest (`Low`) first, tasks without a date always come last.
+/// On the same day, tasks due at a time come before those due by the end of the day.
+fn cmp_dates_none_last(a: &Task, b: &Task, order: SortOrder) -> Ordering {
+    let due = |t: &Task| t.date.0.map(|d| due_instant(d, t.due_time.as_ref()));
+    match (due(a), due(b)) {
+        (Some(a), Some(b)) => match order {
+            SortOrder::High => b.cmp(&a),
+            SortOrder::Low => a.cmp(&b),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
@@ -0,0 +1,395 @@
+use crate::date::{format_due, parse_due, DueTime, TaskDate, INVALID_DUE, TIME_FORMAT};
+use crate::error::TaskError;
+use crate::export::{export_tasks_to_icalendar, import_tasks_from_icalendar, write_to_file};
+use crate::project::INBOX_ID;
+use crate::recurrence::Recurrence;
+use crate::task::{parse_tags, Priority, Task};
+use crate::task_manager::{SortOrder, TasksService};
+use anyhow::{anyhow, Context, Result};
+use chrono::{Local, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Environment variable holding the database path, `--db` takes precedence
+pub const DB_ENV_VAR: &str = "TASKRS_DB";
+
+/// A simple task manager. Without a subcommand the interactive interface is started.
+#[derive(Debug, Parser)]
+#[command(name = "taskrs", version, about)]
+pub struct Cli {
+    /// Database file, by default $XDG_DATA_HOME/task-rustler/tasks.db
+    #[arg(long, global = true, env = DB_ENV_VAR, value_name = "PATH")]
+    pub db: Option<PathBuf>,
+    /// Use a throwaway in-memory database
+    #[arg(long, global = true, conflicts_with = "db")]
+    pub memory: bool,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+impl Cli {
+    /// Path to pass to `TasksService::new`: an empty string for `--memory`, else
+    /// the database file, whose parent directories are created if needed.
+    pub fn db_path(&self) -> Result<String> {
+        if self.memory {
+            return Ok(String::new());
+        }
+        let path = match &self.db {
+            Some(path) => path.clone(),
+            None => default_db_path(
+                env::var_os("XDG_DATA_HOME").map(PathBuf::from),
+                env::var_os("HOME").map(PathBuf::from),
+            )?,
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Can't create directory {}", parent.display()))?;
+        }
+        Ok(path.to_string_lossy().into_owned())
+    }
+}
+
+/// `tasks.db` in the task-rustler data directory, under `xdg_data_home` or
+/// `~/.local/share` when it isn't set, as the XDG base directory spec says.
+pub fn default_db_path(xdg_data_home: Option<PathBuf>, home: Option<PathBuf>) -> Result<PathBuf> {
+    let data_home = match xdg_data_home.filter(|p| p.is_absolute()) {
+        Some(dir) => dir,
+        None => home
+            .map(|h| h.join(".local").join("share"))
+            .ok_or_else(|| anyhow!("Can't find a data directory, set --db or {DB_ENV_VAR}"))?,
+    };
+    Ok(data_home.join("task-rustler").join("tasks.db"))
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Add a new task and print its id
+    Add {
+        title: String,
+        #[arg(long, default_value = "")]
+        desc: String,
+        /// Due date like dd-mm-yyyy, fri or +3d, optionally followed by HH:MM and a timezone
+        #[arg(long)]
+        due: Option<String>,
+        #[arg(long, default_value = "low")]
+        priority: Priority,
+        /// Tag the task, can be repeated
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Project of the task, created if it doesn't exist. The inbox by default.
+        #[arg(long)]
+        project: Option<String>,
+        /// Add the task as a subtask of this one, in the same project
+        #[arg(long, value_name = "ID", conflicts_with = "project")]
+        parent: Option<i32>,
+        /// Repeat the task: daily, "weekly mon,fri", "monthly 15" or "every 3 days"
+        #[arg(long, value_name = "RULE")]
+        repeat: Option<Recurrence>,
+    },
+    /// List tasks, highest priority first
+    List {
+        /// Print the tasks as a JSON array
+        #[arg(long)]
+        json: bool,
+        #[arg(long, value_enum, default_value_t = Filter::All)]
+        filter: Filter,
+        /// Only the tasks with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only the tasks of this project
+        #[arg(long)]
+        project: Option<String>,
+    },
+    /// Mark a task as completed, for a recurring task print the id of the next occurrence
+    Done { id: i32 },
+    /// Delete a task
+    Rm { id: i32 },
+    /// Change the fields of a task, the ones not given are left as they are
+    Edit {
+        id: i32,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        desc: Option<String>,
+        /// Due date like dd-mm-yyyy, fri or +3d, optionally followed by HH:MM and a timezone
+        #[arg(long, conflicts_with = "no_due")]
+        due: Option<String>,
+        /// Remove the due date and time
+        #[arg(long)]
+        no_due: bool,
+        #[arg(long)]
+        priority: Option<Priority>,
+        /// Repeat the task: daily, "weekly mon,fri", "monthly 15" or "every 3 days"
+        #[arg(long, value_name = "RULE", conflicts_with = "no_repeat")]
+        repeat: Option<Recurrence>,
+        /// Stop repeating the task
+        #[arg(long)]
+        no_repeat: bool,
+    },
+    /// Export all the tasks
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Ics)]
+        format: ExportFormat,
+        /// Output file, standard output if missing
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Only the tasks of this project, the calendar is named after it
+        #[arg(long)]
+        project: Option<String>,
+    },
+    /// Import the to-dos of an .ics file, skipping those imported before
+    Import {
+        file: String,
+        /// Project of the imported tasks, created if it doesn't exist
+        #[arg(long)]
+        project: Option<String>,
+    },
+    /// List the projects with their number of tasks
+    Projects,
+    /// Move a task to another project, created if it doesn't exist
+    Mv { id: i32, project: String },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Filter {
+    All,
+    Pending,
+    Done,
+    /// Pending tasks past their due date and time
+    Overdue,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// iCalendar
+    Ics,
+}
+
+impl Filter {
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Pending => !task.completed,
+            Filter::Done => task.completed,
+            Filter::Overdue => task.is_overdue(Utc::now()),
+        }
+    }
+}
+
+/// Run a non-interactive `command` against `tasks`, writing its output to `out`
+pub fn run(command: CliCommand, tasks: &TasksService, out: &mut impl Write) -> Result<()> {
+    match command {
+        CliCommand::Add {
+            title,
+            desc,
+            due,
+            priority,
+            tags,
+            project,
+            parent,
+            repeat,
+        } => {
+            if title.trim().is_empty() {
+                return Err(anyhow!("You must insert at least a title for the task"));
+            }
+            let mut t = Task::new();
+            t.title = title;
+            t.description = desc;
+            t.priority = priority;
+            t.tags = parse_tags(&tags.join(","));
+            t.recurrence = repeat;
+            if let Some(due) = due {
+                (t.date, t.due_time) = parse_date(&due)?;
+            }
+            t.project_id = match project {
+                Some(name) => project_id_or_create(tasks, &name)?,
+                None => INBOX_ID,
+            };
+            let id = match parent {
+                Some(parent_id) => tasks.add_subtask(parent_id, &t)?,
+                None => tasks.add_new_task(&t)?,
+            };
+            writeln!(out, "{id}")?;
+        }
+        CliCommand::List {
+            json,
+            filter,
+            tag,
+            project,
+        } => {
+            let all = match tag {
+                Some(tag) => tasks.tasks_with_tag(&tag)?,
+                None => tasks.get_all_tasks_sorted_by_priority(SortOrder::High)?,
+            };
+            let project_id = match project {
+                Some(name) => Some(tasks.project_by_name(&name)?.id),
+                None => None,
+            };
+            let list: Vec<Task> = all
+                .into_iter()
+                .filter(|t| filter.matches(t) && project_id.is_none_or(|id| t.project_id == id))
+                .collect();
+            if json {
+                let values: Vec<_> = list.iter().map(task_to_json).collect();
+                writeln!(out, "{}", serde_json::to_string_pretty(&values)?)?;
+            } else {
+                for t in &list {
+                    writeln!(out, "{}", format_task_line(t))?;
+                }
+            }
+        }
+        CliCommand::Done { id } => {
+            if let Some(next) = tasks.complete_task(id, Local::now().date_naive())? {
+                writeln!(out, "{next}")?;
+            }
+        }
+        CliCommand::Rm { id } => {
+            if tasks.delete_task(id)? == 0 {
+                return Err(TaskError::NotFound(id).into());
+            }
+        }
+        CliCommand::Edit {
+            id,
+            title,
+            desc,
+            due,
+            no_due,
+            priority,
+            repeat,
+            no_repeat,
+        } => {
+            let mut t = tasks.get_task(id)?.ok_or(TaskError::NotFound(id))?;
+            if let Some(title) = title {
+                if title.trim().is_empty() {
+                    return Err(anyhow!("You must insert at least a title for the task"));
+                }
+                t.title = title;
+            }
+            if let Some(desc) = desc {
+                t.description = desc;
+            }
+            if let Some(due) = due {
+                (t.date, t.due_time) = parse_date(&due)?;
+            } else if no_due {
+                t.date = TaskDate(None);
+                t.due_time = None;
+            }
+            if repeat.is_some() || no_repeat {
+                t.recurrence = repeat;
+            }
+            tasks.update_task(&t)?;
+            if let Some(priority) = priority {
+                tasks.change_priority(id, &priority)?;
+            }
+        }
+        CliCommand::Export {
+            format,
+            output,
+            project,
+        } => match format {
+            ExportFormat::Ics => {
+                let calendar = match project {
+                    Some(name) => {
+                        let project = tasks.project_by_name(&name)?;
+                        export_tasks_to_icalendar(
+                            &project.name,
+                            tasks.database_id(),
+                            &tasks.tasks_in_project(project.id)?,
+                        )
+                    }
+                    None => export_tasks_to_icalendar(
+                        "task rustler",
+                        tasks.database_id(),
+                        &tasks.get_all_tasks()?,
+                    ),
+                };
+                match output {
+                    Some(path) => write_to_file(path.as_str(), calendar.to_string().as_str())?,
+                    None => write!(out, "{calendar}")?,
+                }
+            }
+        },
+        CliCommand::Import { file, project } => {
+            let project_id = match project {
+                Some(name) => project_id_or_create(tasks, &name)?,
+                None => INBOX_ID,
+            };
+            let mut imported = import_tasks_from_icalendar(&file)?;
+            for (_, task) in &mut imported {
+                task.project_id = project_id;
+            }
+            writeln!(out, "{}", tasks.import_tasks(&imported)?)?;
+        }
+        CliCommand::Projects => {
+            for (project, count) in tasks.projects()? {
+                writeln!(out, "{:>4} {}", count, project.name)?;
+            }
+        }
+        CliCommand::Mv { id, project } => {
+            let project_id = project_id_or_create(tasks, &project)?;
+            if tasks.move_task(id, project_id)? == 0 {
+                return Err(TaskError::NotFound(id).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn project_id_or_create(tasks: &TasksService, name: &str) -> Result<i32> {
+    if name.trim().is_empty() {
+        return Err(anyhow!("You must insert a name for the project"));
+    }
+    match tasks.project_by_name(name) {
+        Ok(project) => Ok(project.id),
+        Err(TaskError::ProjectNotFound(_)) => Ok(tasks.add_project(name)?),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn parse_date(date: &str) -> Result<(TaskDate, Option<DueTime>)> {
+    parse_due(date, Local::now().date_naive()).context(INVALID_DUE)
+}
+
+fn format_task_line(task: &Task) -> String {
+    let status = if task.completed { "[x]" } else { "[ ]" };
+    let date = format_due(&task.date, task.due_time.as_ref());
+    let mut line = format!(
+        "{:>4} {status} {:<6} {:<10} {}",
+        task.id,
+        task.priority.name(),
+        date,
+        task.title
+    );
+    if !task.description.is_empty() {
+        line.push_str(" - ");
+        line.push_str(&task.description);
+    }
+    for tag in &task.tags {
+        line.push_str(" #");
+        line.push_str(tag);
+    }
+    if let Some(recurrence) = &task.recurrence {
+        line.push_str(&format!(" ({recurrence})"));
+    }
+    line
+}
+
+fn task_to_json(task: &Task) -> serde_json::Value {
+    json!({
+        "id": task.id,
+        "title": task.title,
+        "description": task.description,
+        "completed": task.completed,
+        "priority": task.priority.name(),
+        "due": task.date.0.map(|d| d.format("%Y-%m-%d").to_string()),
+        "due_time": task.due_time.map(|d| d.time.format(TIME_FORMAT).to_string()),
+        "timezone": task.due_time.and_then(|d| d.timezone).map(|tz| tz.name()),
+        "tags": task.tags,
+        "parent_id": task.parent_id,
+        "repeat": task.recurrence.as_ref().map(|r| r.to_string()),
+    })
+}
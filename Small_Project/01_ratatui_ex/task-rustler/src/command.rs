@@ -1,9 +1,12 @@
 use std::env;
 use crate::app::{AppContext, InputFieldType, InputMode};
-use crate::date::{TaskDate, DATE_FORMAT};
-use crate::task::Task;
+use crate::date::{format_due, parse_due, DueTime, TaskDate, INVALID_DUE};
+use crate::task::{parse_tags, Task};
 use anyhow::{anyhow, Context, Result};
-use crate::export::{export_tasks_to_icalendar, write_to_file};
+use chrono::Local;
+use crate::export::{export_tasks_to_icalendar, import_tasks_from_icalendar, write_to_file};
+use crate::project::INBOX_ID;
+use crate::recurrence::Recurrence;
 
 pub trait Command {
     fn execute(&self, app: &mut AppContext) -> Result<()>;
@@ -20,6 +23,20 @@ impl Command for EnterAddModeCommand {
     }
 }
 
+/// Enter in add command input mode, the new task is a subtask of the selected one
+pub struct EnterAddSubtaskModeCommand;
+
+impl Command for EnterAddSubtaskModeCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        if let Some(id) = app.selected_task_id() {
+            app.new_task_parent = Some(id);
+            app.input_mode = InputMode::Adding;
+            app.input_field = InputFieldType::Title;
+        }
+        Ok(())
+    }
+}
+
 /// Add a new task
 pub struct AddTaskCommand;
 
@@ -29,18 +46,45 @@ impl Command for AddTaskCommand {
         if app.input_title.is_empty() {
             return Err(anyhow!("You must insert at least a title for the task"));
         }
-        if !app.input_date.is_empty() {
-            t.date = TaskDate::try_from(app.input_date.drain(..).collect::<String>())
-                .context("Invalid date format, use dd-mm-yyyy")?;
-        }
+        // Nothing is taken from the inputs until they are all valid, so a typo can be fixed
+        (t.date, t.due_time) = parse_due_input(&app.input_date)?;
+        t.recurrence = parse_recurrence(&app.input_recurrence)?;
+        app.input_date.clear();
+        app.input_recurrence.clear();
         t.title = app.input_title.drain(..).collect();
         t.description = app.input_description.drain(..).collect();
-        app.tasks_service.add_new_task(&t);
-        app.refresh_task_list();
+        t.tags = parse_tags(&app.input_tags.drain(..).collect::<String>());
+        t.project_id = app.project_filter.unwrap_or(INBOX_ID);
+        match app.new_task_parent.take() {
+            Some(parent_id) => {
+                app.tasks_service.add_subtask(parent_id, &t)?;
+                app.collapsed.remove(&parent_id);
+            }
+            None => {
+                app.tasks_service.add_new_task(&t)?;
+            }
+        }
+        app.refresh_task_list()?;
         Ok(())
     }
 }
 
+// An empty date field means the task has no due date.
+fn parse_due_input(input: &str) -> Result<(TaskDate, Option<DueTime>)> {
+    if input.is_empty() {
+        return Ok((TaskDate(None), None));
+    }
+    parse_due(input, Local::now().date_naive()).context(INVALID_DUE)
+}
+
+// An empty repeat field means the task doesn't repeat.
+fn parse_recurrence(input: &str) -> Result<Option<Recurrence>> {
+    if input.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(input.parse().map_err(|e: String| anyhow!(e))?))
+}
+
 /// Start editing a task, move cursor to Title input field
 /// and set InputMode equal to InputMode::EditingExisting
 pub struct StartEditingExistingTaskCommand;
@@ -50,12 +94,16 @@ impl Command for StartEditingExistingTaskCommand {
         if let Some(index) = app.task_list.state.selected() {
             app.input_title = app.task_list.items[index].title.clone();
             app.input_description = app.task_list.items[index].description.clone();
-            app.input_date = app.task_list.items[index]
-                .date
-                .clone()
-                .0
-                .map(|d| d.format(DATE_FORMAT).to_string())
-                .unwrap_or(String::new());
+            app.input_date = format_due(
+                &app.task_list.items[index].date,
+                app.task_list.items[index].due_time.as_ref(),
+            );
+            app.input_tags = app.task_list.items[index].tags.join(", ");
+            app.input_recurrence = app.task_list.items[index]
+                .recurrence
+                .as_ref()
+                .map(|r| r.to_string())
+                .unwrap_or_default();
             app.input_mode = InputMode::EditingExisting;
             app.input_field = InputFieldType::Title;
         }
@@ -72,41 +120,122 @@ impl Command for FinishEditingExistingTaskCommand {
             if app.input_title.is_empty() {
                 return Err(anyhow!("You must insert at least a title for the task"));
             }
-            if !app.input_date.is_empty() {
-                app.task_list.items[index].date =
-                    TaskDate::try_from(app.input_date.drain(..).collect::<String>())
-                        .context("Invalid date format, use dd-mm-yyyy")?;
-            } else {
-                app.task_list.items[index].date = TaskDate(None)
+            // The task is left as it was if any of the inputs is invalid
+            let (date, due_time) = parse_due_input(&app.input_date)?;
+            let recurrence = parse_recurrence(&app.input_recurrence)?;
+            app.input_date.clear();
+            app.input_recurrence.clear();
+            let task = &mut app.task_list.items[index];
+            task.date = date;
+            task.due_time = due_time;
+            task.recurrence = recurrence;
+            task.title = app.input_title.drain(..).collect();
+            task.description = app.input_description.drain(..).collect();
+            task.tags = parse_tags(&app.input_tags.drain(..).collect::<String>());
+            app.tasks_service.update_task(task)?;
+            if app.tag_filter.is_some() {
+                // The task may not have the filtered tag anymore
+                app.refresh_task_list()?;
             }
-            app.task_list.items[index].title = app.input_title.drain(..).collect();
-            app.task_list.items[index].description = app.input_description.drain(..).collect();
-            app.tasks_service.update_task(&app.task_list.items[index])
         }
         Ok(())
     }
 }
 
 
-/// Toggle completed for selected task status
+/// Toggle completed for selected task status. Completing a task with open
+/// subtasks asks first whether they are done too, completing a recurring task
+/// adds its next occurrence.
 pub struct ToggleTaskStatusCommand;
 
 impl Command for ToggleTaskStatusCommand {
     fn execute(&self, app: &mut AppContext) -> Result<()> {
         if let Some(index) = app.task_list.state.selected() {
             let item = &mut app.task_list.items[index];
-            item.completed = match item.completed {
-                true => false,
-                false => true,
-            };
-            let _ = app
-                .tasks_service
-                .toggle_task_status(item.id, item.completed);
+            let completed = !item.completed;
+            if completed
+                && app
+                    .subtask_counts
+                    .get(&item.id)
+                    .is_some_and(|(done, total)| done < total)
+            {
+                app.input_mode = InputMode::ConfirmCompleteSubtasks;
+                return Ok(());
+            }
+            if completed && item.recurrence.is_some() {
+                app.tasks_service
+                    .complete_task(item.id, Local::now().date_naive())?;
+                app.refresh_task_list()?;
+                return Ok(());
+            }
+            app.tasks_service.toggle_task_status(item.id, completed)?;
+            item.completed = completed;
+            if item.parent_id.is_some() {
+                app.subtask_counts = app.tasks_service.subtask_counts()?;
+            }
         };
         Ok(())
     }
 }
 
+/// Complete the selected task, answering the question asked by `ToggleTaskStatusCommand`
+pub struct CompleteTaskCommand {
+    pub with_subtasks: bool,
+}
+
+impl Command for CompleteTaskCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        app.input_mode = InputMode::View;
+        if let Some(id) = app.selected_task_id() {
+            if self.with_subtasks {
+                app.tasks_service.set_status_with_subtasks(id, true)?;
+            }
+            app.tasks_service
+                .complete_task(id, Local::now().date_naive())?;
+            app.refresh_task_list()?;
+        }
+        Ok(())
+    }
+}
+
+/// Leave the selected task as it was instead of completing it
+pub struct CancelCompleteTaskCommand;
+
+impl Command for CancelCompleteTaskCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        app.input_mode = InputMode::View;
+        Ok(())
+    }
+}
+
+/// Hide the subtasks of the selected task
+pub struct CollapseTaskCommand;
+
+impl Command for CollapseTaskCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        if let Some(id) = app.selected_task_id() {
+            if app.subtask_counts.contains_key(&id) && app.collapsed.insert(id) {
+                app.refresh_task_list()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Show again the subtasks of the selected task
+pub struct ExpandTaskCommand;
+
+impl Command for ExpandTaskCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        if let Some(id) = app.selected_task_id() {
+            if app.collapsed.remove(&id) {
+                app.refresh_task_list()?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Switch between priorities
 pub struct ToggleItemPriorityCommand;
 
@@ -114,8 +243,9 @@ impl Command for ToggleItemPriorityCommand {
     fn execute(&self, app: &mut AppContext) -> Result<()> {
         if let Some(index) = app.task_list.state.selected() {
             let item = &mut app.task_list.items[index];
-            item.priority = item.priority.next();
-            app.tasks_service.change_priority(item.id, &item.priority);
+            let priority = item.priority.next();
+            app.tasks_service.change_priority(item.id, &priority)?;
+            item.priority = priority;
         }
         Ok(())
     }
@@ -126,13 +256,138 @@ pub struct DeleteTaskCommand;
 impl Command for DeleteTaskCommand {
     fn execute(&self, app: &mut AppContext) -> Result<()> {
         if let Some(index) = app.task_list.state.selected() {
-            app.tasks_service.delete_task(app.task_list.items[index].id);
-            app.task_list.items.remove(index);
+            app.tasks_service.delete_task(app.task_list.items[index].id)?;
+            // Subtasks are gone too
+            app.refresh_task_list()?;
         }
         Ok(())
     }
 }
 
+/// Show only the tasks with the next tag, cycling through all the tags
+/// and back to the whole list
+pub struct FilterByTagCommand;
+
+impl Command for FilterByTagCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        let tags = app.tasks_service.all_tags()?;
+        let next = match &app.tag_filter {
+            None => 0,
+            Some(current) => tags
+                .iter()
+                .position(|t| t == current)
+                .map_or(0, |i| i + 1),
+        };
+        app.tag_filter = tags.get(next).cloned();
+        app.refresh_task_list()?;
+        app.select_none();
+        Ok(())
+    }
+}
+
+/// View the tasks of the next project, after the last one all the tasks are listed
+pub struct NextProjectCommand;
+
+impl Command for NextProjectCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        app.cycle_project(1);
+        app.refresh_task_list()?;
+        app.select_none();
+        Ok(())
+    }
+}
+
+/// View the tasks of the previous project
+pub struct PreviousProjectCommand;
+
+impl Command for PreviousProjectCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        app.cycle_project(-1);
+        app.refresh_task_list()?;
+        app.select_none();
+        Ok(())
+    }
+}
+
+pub struct EnterNewProjectModeCommand;
+
+impl Command for EnterNewProjectModeCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        app.input_mode = InputMode::NewProject;
+        app.input_project_name.clear();
+        app.error = None;
+        Ok(())
+    }
+}
+
+/// Create the project named in the input field and view it
+pub struct FinishNewProjectCommand;
+
+impl Command for FinishNewProjectCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        if app.input_project_name.trim().is_empty() {
+            return Err(anyhow!("You must insert a name for the project"));
+        }
+        let id = app.tasks_service.add_project(&app.input_project_name)?;
+        app.input_project_name.clear();
+        app.project_filter = Some(id);
+        app.refresh_task_list()?;
+        app.select_none();
+        Ok(())
+    }
+}
+
+/// Start picking the project to move the selected task to
+pub struct EnterMoveTaskModeCommand;
+
+impl Command for EnterMoveTaskModeCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        if let Some(index) = app.task_list.state.selected() {
+            let project_id = app.task_list.items[index].project_id;
+            app.move_target = app
+                .projects
+                .iter()
+                .position(|(p, _)| p.id == project_id)
+                .unwrap_or(0);
+            app.input_mode = InputMode::MoveTask;
+            app.error = None;
+        }
+        Ok(())
+    }
+}
+
+/// Move the selected task to the picked project
+pub struct FinishMoveTaskCommand;
+
+impl Command for FinishMoveTaskCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        if let (Some(index), Some((project, _))) =
+            (app.task_list.state.selected(), app.projects.get(app.move_target))
+        {
+            app.tasks_service
+                .move_task(app.task_list.items[index].id, project.id)?;
+            app.refresh_task_list()?;
+            if app.project_filter.is_some() {
+                // The task left the list
+                app.select_none();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Leave project creation or task moving without changes
+pub struct ExitProjectModeCommand;
+
+impl Command for ExitProjectModeCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        app.input_mode = InputMode::View;
+        app.input_project_name.clear();
+        app.error = None;
+        Ok(())
+    }
+}
+
 /// Stop adding or editing the current task, clear the input fields and
 /// set InputMode back to Normal
 pub struct StopEditingCommand;
@@ -143,6 +398,9 @@ impl Command for StopEditingCommand {
         app.input_title.clear();
         app.input_description.clear();
         app.input_date.clear();
+        app.input_tags.clear();
+        app.input_recurrence.clear();
+        app.new_task_parent = None;
         app.error = None;
         Ok(())
     }
@@ -163,7 +421,13 @@ impl Command for EnterExportModeCommand {
 pub struct FinishingExportCommand;
 impl Command for FinishingExportCommand {
     fn execute(&self, app: &mut AppContext) -> Result<()> {
-        let calendar = export_tasks_to_icalendar("task rustler", &app.task_list.items);
+        // Only the listed tasks are exported, so a project export is named after it
+        let name = app.current_project().map_or("task rustler", |p| p.name.as_str());
+        let calendar = export_tasks_to_icalendar(
+            name,
+            app.tasks_service.database_id(),
+            &app.task_list.items,
+        );
         write_to_file(app.input_export_path.as_str(), calendar.to_string().as_str())
     }
 }
@@ -177,3 +441,40 @@ impl Command for ExitExportModeCommand {
         Ok(())
     }
 }
+
+pub struct EnterImportModeCommand;
+impl Command for EnterImportModeCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        app.input_mode = InputMode::Import;
+        app.error = None;
+        let mut current_dir = env::current_dir().context("Could not access to the current directory")?;
+        current_dir.set_file_name("task_rustler.ics");
+        app.input_import_path = current_dir.display().to_string();
+        Ok(())
+    }
+}
+
+/// Import the tasks of the calendar file into the viewed project, or the Inbox
+pub struct FinishingImportCommand;
+impl Command for FinishingImportCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        let mut tasks = import_tasks_from_icalendar(app.input_import_path.as_str())?;
+        for (_, task) in &mut tasks {
+            task.project_id = app.project_filter.unwrap_or(INBOX_ID);
+        }
+        app.tasks_service.import_tasks(&tasks)?;
+        app.input_import_path.clear();
+        app.refresh_task_list()?;
+        Ok(())
+    }
+}
+
+pub struct ExitImportModeCommand;
+impl Command for ExitImportModeCommand {
+    fn execute(&self, app: &mut AppContext) -> Result<()> {
+        app.input_mode = InputMode::View;
+        app.input_import_path.clear();
+        app.error = None;
+        Ok(())
+    }
+}
@@ -1,7 +1,156 @@
-use anyhow::Result;
-use chrono::NaiveDate;
+use anyhow::{anyhow, Result};
+use chrono::{
+    DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta,
+    TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use std::fmt::{Display, Formatter};
 
+/// Format of dates shown and typed in by the user
 pub const DATE_FORMAT: &str = "%d-%m-%Y";
+/// ISO 8601 dates, also accepted when typed in
+const ISO_DATE_FORMAT: &str = "%Y-%m-%d";
+/// ISO 8601 format dates are stored with, so they sort chronologically as text
+pub const STORAGE_DATE_FORMAT: &str = "%Y-%m-%d";
+/// Format of the time of day, typed after the date and stored as is
+pub const TIME_FORMAT: &str = "%H:%M";
+/// Error for a due date `parse_due` can't read, naming the forms it takes
+pub const INVALID_DUE: &str = "Invalid date, use dd-mm-yyyy, yyyy-mm-dd, today, tomorrow, fri, \
+next mon, +3d, +2w or eom, optionally followed by HH:MM and a timezone";
+
+/// Time of day a task is due, in `timezone` or in local time when there is none
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct DueTime {
+    pub time: NaiveTime,
+    pub timezone: Option<Tz>,
+}
+
+impl DueTime {
+    /// The instant it is due on `date`
+    pub fn on(&self, date: NaiveDate) -> DateTime<Utc> {
+        let naive = date.and_time(self.time);
+        match self.timezone {
+            Some(tz) => to_utc(&tz, naive),
+            None => to_utc(&Local, naive),
+        }
+    }
+}
+
+impl Display for DueTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.time.format(TIME_FORMAT))?;
+        if let Some(tz) = self.timezone {
+            write!(f, " {}", tz.name())?;
+        }
+        Ok(())
+    }
+}
+
+// A time repeated when clocks go back is taken the first time, one skipped when
+// they go forward is moved an hour later.
+fn to_utc<T: TimeZone>(tz: &T, naive: NaiveDateTime) -> DateTime<Utc> {
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|| naive.and_utc())
+}
+
+/// The instant a task due on `date` is overdue from: its due time when it has
+/// one, otherwise the local midnight ending the day.
+pub fn due_instant(date: NaiveDate, due_time: Option<&DueTime>) -> DateTime<Utc> {
+    match due_time {
+        Some(due_time) => due_time.on(date),
+        None => to_utc(&Local, (date + Days::new(1)).and_time(NaiveTime::MIN)),
+    }
+}
+
+/// Resolve a date typed by the user, relative to `today`. Besides `dd-mm-yyyy` and
+/// `yyyy-mm-dd` it takes `today`, `tomorrow`, a weekday like `fri` (the next one,
+/// never today), `next monday` (that day of next week), `+3d`, `+2w` and `eom`,
+/// the last day of the month.
+pub fn resolve_date(input: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let lower = input.trim().to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let date = match words.as_slice() {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["eom"] => today
+            .with_day(1)
+            .and_then(|first| first.checked_add_months(Months::new(1)))
+            .and_then(|next| next.pred_opt()),
+        ["next", day] => day.parse::<Weekday>().ok().map(|day| {
+            today.week(Weekday::Mon).first_day() + Days::new(7 + day.num_days_from_monday() as u64)
+        }),
+        [word] => match word.parse::<Weekday>() {
+            Ok(day) => (1..=7)
+                .map(|i| today + Days::new(i))
+                .find(|d| d.weekday() == day),
+            Err(_) => resolve_offset(word, today).or_else(|| {
+                NaiveDate::parse_from_str(word, DATE_FORMAT)
+                    .or_else(|_| NaiveDate::parse_from_str(word, ISO_DATE_FORMAT))
+                    .ok()
+            }),
+        },
+        _ => None,
+    };
+    date.ok_or_else(|| anyhow!("Unknown date {}", input.trim()))
+}
+
+// `+3d` or `+2w` from `today`
+fn resolve_offset(word: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let offset = word.strip_prefix('+')?;
+    let (count, unit) = offset.split_at(offset.len().checked_sub(1)?);
+    let days = match unit {
+        "d" => count.parse::<u64>().ok()?,
+        "w" => count.parse::<u64>().ok()?.checked_mul(7)?,
+        _ => return None,
+    };
+    today.checked_add_days(Days::new(days))
+}
+
+/// Parse a due date as typed by the user: a date `resolve_date` understands,
+/// optionally followed by the time `HH:MM` and then by a timezone, `UTC` or a
+/// name like `Europe/Rome`
+pub fn parse_due(input: &str, today: NaiveDate) -> Result<(TaskDate, Option<DueTime>)> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let time_at = words
+        .iter()
+        .position(|w| NaiveTime::parse_from_str(w, TIME_FORMAT).is_ok())
+        .unwrap_or(words.len());
+    let date = resolve_date(&words[..time_at].join(" "), today)?;
+    let mut parts = words[time_at..].iter();
+    let due_time = match parts.next() {
+        Some(time) => Some(DueTime {
+            time: NaiveTime::parse_from_str(time, TIME_FORMAT)?,
+            timezone: parts
+                .next()
+                .map(|tz| {
+                    tz.parse::<Tz>()
+                        .map_err(|_| anyhow!("Unknown timezone {tz}"))
+                })
+                .transpose()?,
+        }),
+        None => None,
+    };
+    if let Some(extra) = parts.next() {
+        return Err(anyhow!("Unexpected {extra} after the due date"));
+    }
+    Ok((TaskDate(Some(date)), due_time))
+}
+
+/// The due date the way `parse_due` reads it, empty without a date
+pub fn format_due(date: &TaskDate, due_time: Option<&DueTime>) -> String {
+    match (date.0, due_time) {
+        (Some(date), Some(due_time)) => format!("{} {due_time}", date.format(DATE_FORMAT)),
+        (Some(date), None) => date.format(DATE_FORMAT).to_string(),
+        (None, _) => String::new(),
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Ord, PartialOrd)]
 pub struct TaskDate(pub Option<NaiveDate>);
 
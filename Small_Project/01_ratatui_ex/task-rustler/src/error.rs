@@ -0,0 +1,34 @@
+use thiserror::Error;
+
+/// Errors of the task database and of `TasksService`
+#[derive(Debug, Error)]
+pub enum TaskError {
+    #[error("Can't open database {path}")]
+    Open {
+        path: String,
+        source: rusqlite::Error,
+    },
+    #[error("Database schema version {found} is newer than the supported {supported}, please update task rustler")]
+    SchemaTooNew { found: u32, supported: u32 },
+    #[error("Can't back up the database to {path}")]
+    Backup {
+        path: String,
+        source: rusqlite::Error,
+    },
+    #[error("Migration to schema version {version} ({description}) failed")]
+    Migration {
+        version: u32,
+        description: &'static str,
+        source: rusqlite::Error,
+    },
+    #[error("No task with id {0}")]
+    NotFound(i32),
+    #[error("No project named {0}")]
+    ProjectNotFound(String),
+    #[error("There is already a project named {0}")]
+    ProjectExists(String),
+    #[error("Database error")]
+    Db(#[from] rusqlite::Error),
+}
+
+pub type Result<T> = std::result::Result<T, TaskError>;
@@ -1,23 +1,66 @@
-use icalendar::{Calendar, Component, EventLike, Todo, TodoStatus};
-use crate::task::Task;
+use chrono_tz::Tz;
+use icalendar::{Calendar, CalendarDateTime, Component, EventLike, Todo, TodoStatus};
+use crate::task::{Priority, Task};
+use std::collections::HashMap;
 
-pub fn export_tasks_to_icalendar(calendar_name: &'static str, tasks: &[Task]) -> Calendar {
+/// Calendar of `tasks`, from the database with `database_id`
+pub fn export_tasks_to_icalendar(
+    calendar_name: &str,
+    database_id: &str,
+    tasks: &[Task],
+) -> Calendar {
+    let uids: HashMap<i32, String> = tasks
+        .iter()
+        .map(|task| (task.id, uid_of(database_id, task)))
+        .collect();
     let mut calendar = Calendar::new();
     calendar.name(calendar_name);
     for task in tasks {
         calendar.push(
-            build_icalendar_todo_task(task)
+            build_icalendar_todo_task(task, database_id, &uids)
         );
     }
 
     calendar.done()
 }
 
-fn build_icalendar_todo_task(task: &Task) -> Todo {
+/// UID given to the task with `task_id` when it was added to the database with
+/// `database_id`, unique across databases
+pub fn task_uid(database_id: &str, task_id: i32) -> String {
+    format!("{task_id}-{database_id}@task-rustler")
+}
+
+// The stored UID, tasks that weren't saved yet have none
+fn uid_of(database_id: &str, task: &Task) -> String {
+    task.uid
+        .clone()
+        .unwrap_or_else(|| task_uid(database_id, task.id))
+}
+
+fn build_icalendar_todo_task(task: &Task, database_id: &str, uids: &HashMap<i32, String>) -> Todo {
     let mut todo = Todo::new();
-    if let Some(date) = task.date.0{
-        todo.all_day(date);
-    };
+    todo.uid(&uid_of(database_id, task));
+    if let Some(parent_id) = task.parent_id {
+        // A parent left out of the calendar can only be given its UID from the id
+        let parent_uid = match uids.get(&parent_id) {
+            Some(uid) => uid.clone(),
+            None => task_uid(database_id, parent_id),
+        };
+        todo.add_property("RELATED-TO", parent_uid);
+    }
+    if let Some(date) = task.date.0 {
+        match task.due_time {
+            // A time without a zone is local to whoever added it, so it's exported in UTC
+            Some(due_time) => match due_time.timezone {
+                Some(tz) if tz != Tz::UTC => todo.due(CalendarDateTime::WithTimezone {
+                    date_time: date.and_time(due_time.time),
+                    tzid: tz.name().to_string(),
+                }),
+                _ => todo.due(CalendarDateTime::Utc(due_time.on(date))),
+            },
+            None => todo.all_day(date),
+        };
+    }
     todo.summary(task.title.as_str());
     todo.description(task.description.as_str());
     if task.completed {
@@ -25,6 +68,17 @@ fn build_icalendar_todo_task(task: &Task) -> Todo {
     } else {
         todo.status(TodoStatus::NeedsAction);
     }
-    todo.priority(task.priority.to_u8() as u32);
+    // RFC 5545 priorities: 1 to 4 is high, 5 medium and 6 to 9 low
+    todo.priority(match task.priority {
+        Priority::High => 1,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    });
+    for tag in &task.tags {
+        todo.add_multi_property("CATEGORIES", tag);
+    }
+    if let Some(recurrence) = &task.recurrence {
+        todo.add_property("RRULE", recurrence.to_rrule());
+    }
     todo.done()
 }
\ No newline at end of file
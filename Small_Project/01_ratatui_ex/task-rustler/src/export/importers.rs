@@ -0,0 +1,74 @@
+use crate::date::{DueTime, TaskDate};
+use crate::task::{Priority, Task};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveTime, Timelike};
+use chrono_tz::Tz;
+use icalendar::{Calendar, CalendarDateTime, Component, DatePerhapsTime, Todo, TodoStatus};
+use std::fs;
+use std::path::Path;
+
+/// Read the VTODOs of the iCalendar file at `path` as new tasks, each with its UID
+/// so the ones imported before can be skipped.
+pub fn import_tasks_from_icalendar(path: &str) -> Result<Vec<(Option<String>, Task)>> {
+    let path = Path::new(path);
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Error reading file {}: {}", path.display(), e))?;
+    // The parser skips what it doesn't understand, any text would be an empty calendar
+    let start = content.trim_start_matches('\u{feff}').trim_start();
+    if !start
+        .get(..15)
+        .is_some_and(|s| s.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err(anyhow!(
+            "Error parsing calendar {}: not an iCalendar file",
+            path.display()
+        ));
+    }
+    let calendar: Calendar = content
+        .parse()
+        .map_err(|e| anyhow!("Error parsing calendar {}: {}", path.display(), e))?;
+    Ok(calendar
+        .components
+        .iter()
+        .filter_map(|c| c.as_todo())
+        .map(|todo| (todo.get_uid().map(str::to_string), task_from_todo(todo)))
+        .collect())
+}
+
+fn task_from_todo(todo: &Todo) -> Task {
+    let mut task = Task::new();
+    task.title = todo.get_summary().unwrap_or_default().to_string();
+    task.description = todo.get_description().unwrap_or_default().to_string();
+    task.completed = todo.get_status() == Some(TodoStatus::Completed);
+    task.priority = match todo.get_priority() {
+        // RFC 5545: 0 is undefined, 1 to 4 high, 5 medium and 6 to 9 low
+        Some(1..=4) => Priority::High,
+        Some(5) => Priority::Medium,
+        _ => Priority::Low,
+    };
+    if let Some(due) = todo.get_due().or_else(|| todo.get_start()) {
+        (task.date, task.due_time) = date_and_time(due);
+    }
+    task
+}
+
+fn date_and_time(due: DatePerhapsTime) -> (TaskDate, Option<DueTime>) {
+    let (date_time, timezone) = match due {
+        DatePerhapsTime::Date(date) => return (TaskDate(Some(date)), None),
+        DatePerhapsTime::DateTime(CalendarDateTime::Utc(date_time)) => {
+            (date_time.naive_utc(), Some(Tz::UTC))
+        }
+        DatePerhapsTime::DateTime(CalendarDateTime::Floating(date_time)) => (date_time, None),
+        // A TZID that isn't an IANA name is read as local time
+        DatePerhapsTime::DateTime(CalendarDateTime::WithTimezone { date_time, tzid }) => {
+            (date_time, tzid.parse::<Tz>().ok())
+        }
+    };
+    // Due times are kept to the minute
+    let time = date_time.time();
+    let due_time = DueTime {
+        time: NaiveTime::from_hms_opt(time.hour(), time.minute(), 0).unwrap_or(time),
+        timezone,
+    };
+    (TaskDate(Some(date_time.date())), Some(due_time))
+}
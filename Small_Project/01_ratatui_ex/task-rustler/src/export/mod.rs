@@ -1,5 +1,7 @@
 mod exporters;
 mod file_writer;
+mod importers;
 
-pub use exporters::export_tasks_to_icalendar;
-pub use file_writer::write_to_file;
\ No newline at end of file
+pub use exporters::{export_tasks_to_icalendar, task_uid};
+pub use file_writer::write_to_file;
+pub use importers::import_tasks_from_icalendar;
\ No newline at end of file
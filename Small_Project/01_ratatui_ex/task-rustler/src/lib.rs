@@ -1,6 +1,11 @@
 pub mod app;
+pub mod cli;
 pub mod command;
 pub mod date;
+pub mod error;
+pub mod migrations;
+pub mod project;
+pub mod recurrence;
 pub mod task;
 pub mod task_db;
 pub mod task_manager;
@@ -1,15 +1,46 @@
 use ratatui::crossterm::event;
 use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::Terminal;
+use clap::Parser;
 use std::error::Error;
-use std::io;
+use std::{io, process};
 use task_rustler::app::{AppContext, InputMode};
+use task_rustler::cli::{self, Cli};
 use task_rustler::command::*;
+use task_rustler::task_manager::TasksService;
 use task_rustler::ui;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut app = AppContext::new(String::from("tasks.db"));
-    app.refresh_task_list();
+    let args = Cli::parse();
+    let db_path = match args.db_path() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            process::exit(1);
+        }
+    };
+    if let Some(command) = args.command {
+        let res = TasksService::new(db_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|tasks| cli::run(command, &tasks, &mut io::stdout()));
+        if let Err(e) = res {
+            eprintln!("Error: {e:#}");
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Fail before the terminal is switched to raw mode.
+    let mut app = match AppContext::new(db_path) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("Error: {:#}", anyhow::Error::from(e));
+            process::exit(1);
+        }
+    };
+    if let Err(e) = app.refresh_task_list() {
+        app.error = Some(format!("{:#}", anyhow::Error::from(e)));
+    }
     let mut terminal = ratatui::init();
     let res = run_app(&mut terminal, app);
     ratatui::restore();
@@ -50,6 +81,12 @@ fn run_app<B: ratatui::backend::Backend>(
                 InputMode::Adding => handle_key_event_adding_mode(key.code, &mut app),
                 InputMode::EditingExisting => handle_key_event_editing_existing_mode(key.code, &mut app),
                 InputMode::Export => handle_key_event_export_mode(key.code, &mut app),
+                InputMode::Import => handle_key_event_import_mode(key.code, &mut app),
+                InputMode::NewProject => handle_key_event_new_project_mode(key.code, &mut app),
+                InputMode::MoveTask => handle_key_event_move_task_mode(key.code, &mut app),
+                InputMode::ConfirmCompleteSubtasks => {
+                    handle_key_event_confirm_complete_mode(key.code, &mut app)
+                }
             }
         }
     }
@@ -58,7 +95,16 @@ fn run_app<B: ratatui::backend::Backend>(
 fn handle_key_event_view_mode(key: KeyEvent, app: &mut AppContext) {
     match (key.code, key.modifiers) {
         (KeyCode::Char('a'), KeyModifiers::NONE) => {
-            let _ = EnterAddModeCommand.execute(app);
+            handle_errors(EnterAddModeCommand, app);
+        }
+        (KeyCode::Char('A'), KeyModifiers::SHIFT) => {
+            handle_errors(EnterAddSubtaskModeCommand, app);
+        }
+        (KeyCode::Char('c'), KeyModifiers::NONE) => {
+            handle_errors(CollapseTaskCommand, app);
+        }
+        (KeyCode::Char('e'), KeyModifiers::NONE) => {
+            handle_errors(ExpandTaskCommand, app);
         }
         (KeyCode::Down, KeyModifiers::NONE) => {
             app.select_next();
@@ -66,14 +112,26 @@ fn handle_key_event_view_mode(key: KeyEvent, app: &mut AppContext) {
         (KeyCode::Up, KeyModifiers::NONE) => {
             app.select_previous();
         }
+        (KeyCode::Right, KeyModifiers::NONE) => {
+            handle_errors(NextProjectCommand, app);
+        }
+        (KeyCode::Left, KeyModifiers::NONE) => {
+            handle_errors(PreviousProjectCommand, app);
+        }
+        (KeyCode::Char('n'), KeyModifiers::NONE) => {
+            handle_errors(EnterNewProjectModeCommand, app);
+        }
+        (KeyCode::Char('v'), KeyModifiers::NONE) => {
+            handle_errors(EnterMoveTaskModeCommand, app);
+        }
         (KeyCode::Char(' '), KeyModifiers::NONE) => {
-            let _ = ToggleTaskStatusCommand.execute(app);
+            handle_errors(ToggleTaskStatusCommand, app);
         }
         (KeyCode::Char('m'), KeyModifiers::NONE) => {
-            let _ = StartEditingExistingTaskCommand.execute(app);
+            handle_errors(StartEditingExistingTaskCommand, app);
         }
         (KeyCode::Char('p'), KeyModifiers::NONE) => {
-            let _ = ToggleItemPriorityCommand.execute(app);
+            handle_errors(ToggleItemPriorityCommand, app);
         }
         (KeyCode::Char('s'), KeyModifiers::NONE) => {
             app.sort_by_priority();
@@ -81,12 +139,18 @@ fn handle_key_event_view_mode(key: KeyEvent, app: &mut AppContext) {
         (KeyCode::Char('t'), KeyModifiers::NONE) => {
             app.sort_by_date();
         }
+        (KeyCode::Char('f'), KeyModifiers::NONE) => {
+            handle_errors(FilterByTagCommand, app);
+        }
         (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
-            let _ = DeleteTaskCommand.execute(app);
+            handle_errors(DeleteTaskCommand, app);
         }
         (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
             handle_errors(EnterExportModeCommand, app);
         }
+        (KeyCode::Char('o'), KeyModifiers::CONTROL) => {
+            handle_errors(EnterImportModeCommand, app);
+        }
         _ => {}
     }
 }
@@ -146,9 +210,71 @@ fn handle_key_event_export_mode(key: KeyCode, app: &mut AppContext) {
     }
 }
 
+fn handle_key_event_import_mode(key: KeyCode, app: &mut AppContext) {
+    match key {
+        KeyCode::Esc => ExitImportModeCommand.execute(app).unwrap(),
+        KeyCode::Enter => {
+            handle_errors(FinishingImportCommand, app);
+            if app.error.is_none() {
+                app.input_mode = InputMode::View;
+            }
+        }
+        KeyCode::Char(c) => {
+            app.input_import_path.push(c);
+        }
+        KeyCode::Backspace => {
+            app.input_import_path.pop();
+        }
+        _ => {}
+    }
+}
+
+fn handle_key_event_new_project_mode(key: KeyCode, app: &mut AppContext) {
+    match key {
+        KeyCode::Esc => ExitProjectModeCommand.execute(app).unwrap(),
+        KeyCode::Enter => {
+            handle_errors(FinishNewProjectCommand, app);
+            if app.error.is_none() {
+                app.input_mode = InputMode::View;
+            }
+        }
+        KeyCode::Char(c) => {
+            app.input_project_name.push(c);
+        }
+        KeyCode::Backspace => {
+            app.input_project_name.pop();
+        }
+        _ => {}
+    }
+}
+
+fn handle_key_event_move_task_mode(key: KeyCode, app: &mut AppContext) {
+    match key {
+        KeyCode::Esc => ExitProjectModeCommand.execute(app).unwrap(),
+        KeyCode::Enter => {
+            handle_errors(FinishMoveTaskCommand, app);
+            if app.error.is_none() {
+                app.input_mode = InputMode::View;
+            }
+        }
+        KeyCode::Down => app.next_move_target(),
+        KeyCode::Up => app.previous_move_target(),
+        _ => {}
+    }
+}
+
+fn handle_key_event_confirm_complete_mode(key: KeyCode, app: &mut AppContext) {
+    match key {
+        KeyCode::Char('y') => handle_errors(CompleteTaskCommand { with_subtasks: true }, app),
+        KeyCode::Char('n') => handle_errors(CompleteTaskCommand { with_subtasks: false }, app),
+        KeyCode::Esc => CancelCompleteTaskCommand.execute(app).unwrap(),
+        _ => {}
+    }
+}
+
 fn handle_errors<T: Command>(command:T, app: &mut AppContext) {
     if let Err(e) = command.execute(app) {
-        app.error= Some(e.to_string());
+        app.error = Some(format!("{e:#}"));
     } else {
         app.error = None;
     }
@@ -0,0 +1,190 @@
+use crate::error::{Result, TaskError};
+use rusqlite::{params, Connection, Transaction};
+
+/// A schema change, taking the database from `version - 1` to `version`.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    apply: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// Every migration in order. Never edit a released one, add a new step instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the tasks table",
+        // Databases from before migrations have the table already, at version 0.
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY,
+                title TEXT NOT NULL,
+                description TEXT NOT NULL,
+                completed BOOLEAN NOT NULL,
+                priority INTEGER NOT NULL,
+                end_date DATE
+            )",
+            )
+        },
+    },
+    Migration {
+        version: 2,
+        description: "store due dates as ISO 8601",
+        // dd-mm-yyyy -> yyyy-mm-dd
+        apply: |tx| {
+            tx.execute_batch(
+                "UPDATE tasks
+                SET end_date = substr(end_date, 7, 4) || '-' || substr(end_date, 4, 2) || '-' || substr(end_date, 1, 2)
+                WHERE end_date GLOB '[0-9][0-9]-[0-9][0-9]-[0-9][0-9][0-9][0-9]'",
+            )
+        },
+    },
+    Migration {
+        version: 3,
+        description: "add tags",
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE tags (
+                    id INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+                );
+                CREATE TABLE task_tags (
+                    task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                    PRIMARY KEY (task_id, tag_id)
+                );",
+            )
+        },
+    },
+    Migration {
+        version: 4,
+        description: "add projects",
+        // SQLite can't add a referencing column with a default, the existing tasks
+        // are moved to the inbox afterwards.
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE projects (
+                    id INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+                );
+                INSERT INTO projects (id, name) VALUES (1, 'Inbox');
+                ALTER TABLE tasks ADD COLUMN project_id INTEGER REFERENCES projects(id);
+                UPDATE tasks SET project_id = 1;",
+            )
+        },
+    },
+    Migration {
+        version: 5,
+        description: "add subtasks",
+        apply: |tx| {
+            tx.execute_batch(
+                "ALTER TABLE tasks ADD COLUMN parent_id INTEGER REFERENCES tasks(id) ON DELETE CASCADE;",
+            )
+        },
+    },
+    Migration {
+        version: 6,
+        description: "add recurrence",
+        apply: |tx| tx.execute_batch("ALTER TABLE tasks ADD COLUMN recurrence TEXT;"),
+    },
+    Migration {
+        version: 7,
+        description: "add due time and timezone",
+        apply: |tx| {
+            tx.execute_batch(
+                "ALTER TABLE tasks ADD COLUMN due_time TEXT;
+                ALTER TABLE tasks ADD COLUMN timezone TEXT;",
+            )
+        },
+    },
+    Migration {
+        version: 8,
+        description: "remember the UID of imported tasks",
+        apply: |tx| {
+            tx.execute_batch(
+                "ALTER TABLE tasks ADD COLUMN uid TEXT;
+                CREATE UNIQUE INDEX tasks_uid ON tasks(uid);",
+            )
+        },
+    },
+    Migration {
+        version: 9,
+        description: "give every task a UID",
+        // The random id of the database keeps the UIDs of two databases apart.
+        apply: |tx| {
+            tx.execute_batch(
+                "CREATE TABLE settings (
+                    key TEXT PRIMARY KEY,
+                    value TEXT NOT NULL
+                );
+                INSERT INTO settings (key, value) VALUES ('database_id', lower(
+                    hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-' || hex(randomblob(2))
+                    || '-' || hex(randomblob(2)) || '-' || hex(randomblob(6))
+                ));
+                UPDATE tasks
+                SET uid = id || '-' || (SELECT value FROM settings WHERE key = 'database_id') || '@task-rustler'
+                WHERE uid IS NULL;",
+            )
+        },
+    },
+];
+
+/// Schema version of a database once all the migrations are applied
+pub const SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Schema version stored in the database, 0 if it was never migrated
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    Ok(conn.query_row("PRAGMA user_version", [], |r| r.get(0))?)
+}
+
+/// Path of the copy made before migrating the database at `db_path` away from `version`
+pub fn backup_path(db_path: &str, version: u32) -> String {
+    format!("{db_path}.v{version}.bak")
+}
+
+/// Bring the database up to `SCHEMA_VERSION`.
+///
+/// Each step runs in its own transaction together with the `user_version` update,
+/// so a failing step leaves the database at the previous version. A file database
+/// that already holds data is copied next to itself before every step.
+pub fn migrate(conn: &mut Connection) -> Result<()> {
+    let current = schema_version(conn)?;
+    if current > SCHEMA_VERSION {
+        return Err(TaskError::SchemaTooNew {
+            found: current,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        backup(conn, migration.version - 1)?;
+        let tx = conn.transaction()?;
+        (migration.apply)(&tx).map_err(|source| TaskError::Migration {
+            version: migration.version,
+            description: migration.description,
+            source,
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn backup(conn: &Connection, version: u32) -> Result<()> {
+    let Some(path) = conn.path().filter(|p| !p.is_empty()) else {
+        return Ok(());
+    };
+    let objects: i64 = conn.query_row("SELECT count(*) FROM sqlite_master", [], |r| r.get(0))?;
+    if objects == 0 {
+        // A brand new file, nothing worth keeping.
+        return Ok(());
+    }
+    let target = backup_path(path, version);
+    // VACUUM INTO refuses to overwrite, an old backup of the same version is stale.
+    let _ = std::fs::remove_file(&target);
+    conn.execute("VACUUM INTO ?1", params![target])
+        .map_err(|source| TaskError::Backup {
+            path: target,
+            source,
+        })?;
+    Ok(())
+}
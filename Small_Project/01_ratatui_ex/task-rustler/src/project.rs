@@ -0,0 +1,10 @@
+/// A named list of tasks
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Project {
+    pub id: i32,
+    pub name: String,
+}
+
+/// Project of the tasks created without one. Every database has it, tasks from
+/// before projects existed were moved there.
+pub const INBOX_ID: i32 = 1;
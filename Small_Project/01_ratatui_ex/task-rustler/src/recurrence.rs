@@ -0,0 +1,129 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// How often a task repeats. Written and parsed as `daily`, `weekly mon,fri`,
+/// `monthly 15` or `every 3 days`, which is also how it's stored.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Recurrence {
+    Daily,
+    /// On the given days of the week, sorted from Monday and never empty
+    Weekly(Vec<Weekday>),
+    /// On a day of the month, the last day for the months that are too short
+    Monthly(u32),
+    EveryNDays(u32),
+}
+
+impl Recurrence {
+    /// Date of the occurrence following the one due on `date`
+    pub fn next_date(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrence::Daily => date + Days::new(1),
+            Recurrence::EveryNDays(n) => date + Days::new(*n as u64),
+            Recurrence::Weekly(days) => (1..=7)
+                .map(|i| date + Days::new(i))
+                .find(|d| days.contains(&d.weekday()))
+                .unwrap_or(date + Days::new(7)),
+            Recurrence::Monthly(day) => {
+                let this_month = day_in_month(date, *day);
+                if this_month > date {
+                    this_month
+                } else {
+                    let first = date.with_day(1).unwrap() + Months::new(1);
+                    day_in_month(first, *day)
+                }
+            }
+        }
+    }
+
+    /// The rule as an iCalendar `RRULE` value
+    pub fn to_rrule(&self) -> String {
+        match self {
+            Recurrence::Daily => "FREQ=DAILY".to_string(),
+            Recurrence::EveryNDays(n) => format!("FREQ=DAILY;INTERVAL={n}"),
+            Recurrence::Weekly(days) => {
+                let days: Vec<&str> = days.iter().map(|d| &weekday_name(*d)[..2]).collect();
+                format!("FREQ=WEEKLY;BYDAY={}", days.join(",").to_uppercase())
+            }
+            Recurrence::Monthly(day) if *day <= 28 => format!("FREQ=MONTHLY;BYMONTHDAY={day}"),
+            // BYMONTHDAY alone skips the months without that day, taking the last
+            // existing one of 28..=day falls back to the end of the month instead.
+            Recurrence::Monthly(day) => {
+                let days: Vec<String> = (28..=*day).map(|d| d.to_string()).collect();
+                format!("FREQ=MONTHLY;BYMONTHDAY={};BYSETPOS=-1", days.join(","))
+            }
+        }
+    }
+}
+
+// `day` of the month of `date`, or its last day if the month is shorter
+fn day_in_month(date: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|d| date.with_day(d))
+        .unwrap_or(date)
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "mon",
+        Weekday::Tue => "tue",
+        Weekday::Wed => "wed",
+        Weekday::Thu => "thu",
+        Weekday::Fri => "fri",
+        Weekday::Sat => "sat",
+        Weekday::Sun => "sun",
+    }
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekly(days) => {
+                let days: Vec<&str> = days.iter().map(|d| weekday_name(*d)).collect();
+                write!(f, "weekly {}", days.join(","))
+            }
+            Recurrence::Monthly(day) => write!(f, "monthly {day}"),
+            Recurrence::EveryNDays(n) => write!(f, "every {n} days"),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("invalid repeat '{s}', use daily, weekly mon,fri, monthly 15 or every 3 days")
+        };
+        let lower = s.trim().to_lowercase();
+        let words: Vec<&str> = lower.split_whitespace().collect();
+        match words.as_slice() {
+            ["daily"] => Ok(Recurrence::Daily),
+            ["weekly", days] => {
+                let mut days = days
+                    .split(',')
+                    .filter(|d| !d.is_empty())
+                    .map(Weekday::from_str)
+                    .collect::<Result<Vec<Weekday>, _>>()
+                    .map_err(|_| invalid())?;
+                days.sort_by_key(|d| d.num_days_from_monday());
+                days.dedup();
+                if days.is_empty() {
+                    return Err(invalid());
+                }
+                Ok(Recurrence::Weekly(days))
+            }
+            ["monthly", day] => match day.parse() {
+                Ok(day @ 1..=31) => Ok(Recurrence::Monthly(day)),
+                _ => Err(invalid()),
+            },
+            ["every", n, "day" | "days"] => match n.parse() {
+                Ok(n) if n > 0 => Ok(Recurrence::EveryNDays(n)),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
@@ -1,5 +1,9 @@
-use crate::date::TaskDate;
+use crate::date::{due_instant, DueTime, TaskDate};
+use crate::project::INBOX_ID;
+use crate::recurrence::Recurrence;
+use chrono::{DateTime, NaiveDate, Utc};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Priority {
@@ -33,6 +37,28 @@ impl Priority {
             Priority::High => 1,
         }
     }
+
+    /// Lowercase name, as accepted by `from_str`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" | "l" => Ok(Priority::Low),
+            "medium" | "m" => Ok(Priority::Medium),
+            "high" | "h" => Ok(Priority::High),
+            _ => Err(format!("unknown priority '{s}', use high, medium or low")),
+        }
+    }
 }
 
 impl Display for Priority {
@@ -53,6 +79,16 @@ pub struct Task {
     pub completed: bool,
     pub priority: Priority,
     pub date: TaskDate,
+    /// Time of day on `date` the task is due, the whole day when `None`
+    pub due_time: Option<DueTime>,
+    /// Tag names, sorted
+    pub tags: Vec<String>,
+    pub project_id: i32,
+    /// The task this one is a step of
+    pub parent_id: Option<i32>,
+    pub recurrence: Option<Recurrence>,
+    /// iCalendar UID, the one it was imported with or else given when it was added
+    pub uid: Option<String>,
 }
 
 impl Default for Task {
@@ -73,6 +109,45 @@ impl Task {
             completed: false,
             priority: Priority::Low,
             date: TaskDate(None),
+            due_time: None,
+            tags: Vec::new(),
+            project_id: INBOX_ID,
+            parent_id: None,
+            recurrence: None,
+            uid: None,
+        }
+    }
+
+    /// Not completed and past its due time, or past its due day when it has no time
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        !self.completed
+            && self
+                .date
+                .0
+                .is_some_and(|date| now > due_instant(date, self.due_time.as_ref()))
+    }
+
+    /// The pending copy of a recurring task to do after this one, due on the next
+    /// date of the rule counting from this task's date, or from `today` without one.
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<Task> {
+        let recurrence = self.recurrence.as_ref()?;
+        Some(Task {
+            id: 0,
+            completed: false,
+            date: TaskDate(Some(recurrence.next_date(self.date.0.unwrap_or(today)))),
+            uid: None,
+            ..self.clone()
+        })
+    }
+}
+
+/// Split a comma separated list of tags, dropping blanks and repeated names
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
         }
     }
+    tags
 }
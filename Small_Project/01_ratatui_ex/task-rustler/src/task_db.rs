@@ -1,243 +1,518 @@
-use crate::date::{TaskDate, DATE_FORMAT};
+use crate::date::{DueTime, TaskDate, STORAGE_DATE_FORMAT, TIME_FORMAT};
+use crate::error::{Result, TaskError};
+use crate::export::task_uid;
+use crate::migrations;
+use crate::project::Project;
+use crate::recurrence::Recurrence;
 use crate::task::{Priority, Task};
-use anyhow::{Context, Result};
-use chrono::NaiveDate;
-use rusqlite::types::{FromSql, FromSqlResult, ToSqlOutput, ValueRef};
-use rusqlite::{params, Connection, Row, ToSql};
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, Type, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Params, Row, ToSql};
+use std::collections::HashMap;
+
+const SELECT_TASKS: &str =
+    "SELECT id, title, description, completed, priority, end_date, project_id, parent_id, recurrence, due_time, timezone, uid FROM tasks";
+
+/// Ids of the task `?1` and of all its subtasks, at any depth
+const SUBTREE: &str = "WITH RECURSIVE subtree(id) AS (
+    SELECT ?1 UNION ALL SELECT tasks.id FROM tasks JOIN subtree ON tasks.parent_id = subtree.id
+)";
 
 #[derive(Debug)]
 pub struct DB {
     connection: Connection,
+    /// Random id of the database, part of the UID of its tasks
+    database_id: String,
 }
 
 impl DB {
     /// Create and return a connection to a database located at path
     /// if path is an empty string creates and in memory db instance
-    pub fn create_and_return_connection(path: &str) -> DB {
-        let conn: Connection = if path.is_empty() {
+    pub fn create_and_return_connection(path: &str) -> Result<DB> {
+        let conn = if path.is_empty() {
             Connection::open_in_memory()
-                .context("Can't open in-memory DB.")
-                .unwrap()
         } else {
             Connection::open(path)
-                .context("Can't open database")
-                .unwrap()
+        }
+        .map_err(|source| TaskError::Open {
+            path: path.to_string(),
+            source,
+        })?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let mut db = DB {
+            connection: conn,
+            database_id: String::new(),
         };
-        let mut db = DB { connection: conn };
-        db.init();
-        db
+        db.init()?;
+        Ok(db)
     }
 
-    fn init(&mut self) {
-        self.connection
-            .execute(
-                "CREATE TABLE IF NOT EXISTS tasks (
-            id INTEGER PRIMARY KEY,
-            title TEXT NOT NULL,
-            description TEXT NOT NULL,
-            completed BOOLEAN NOT NULL,
-            priority INTEGER NOT NULL,
-            end_date DATE
-        )",
-                [],
-            )
-            .context("Can't create the DB")
-            .unwrap();
+    fn init(&mut self) -> Result<()> {
+        migrations::migrate(&mut self.connection)?;
+        self.database_id = self.connection.query_row(
+            "SELECT value FROM settings WHERE key = 'database_id'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(())
     }
 
-    pub fn insert_task(&self, task: &Task) {
-        self.connection
-            .execute(
-                "INSERT INTO tasks (title, description, completed, priority, end_date) VALUES (?1, ?2, 0, ?3, ?4)",
-                params![
-                    task.title.trim(),
-                    task.description.trim(),
-                    task.priority.to_u8(),
-                    task.date,
-                ],
-            )
-            .context("Can't add task to DB.")
-            .unwrap();
+    pub fn database_id(&self) -> &str {
+        &self.database_id
     }
 
-    pub fn get_all_tasks(&self) -> Vec<Task> {
-        let mut stmt = self
-            .connection
-            .prepare("SELECT id, title, description, completed, priority, end_date FROM tasks")
-            .unwrap();
-        let task_row_iter = stmt
-            .query_map([], |row| Task::try_from(row))
-            .context("Can't get results from DB.")
-            .unwrap();
-        let mut tasks = Vec::new();
-        for task in task_row_iter {
-            tasks.push(task.unwrap());
+    /// Insert `task` and return the id it was given
+    pub fn insert_task(&self, task: &Task) -> Result<i32> {
+        let tx = self.connection.unchecked_transaction()?;
+        let id = insert_task_row(&tx, &self.database_id, task)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Complete the task and add `next`, its next occurrence, which takes over
+    /// the recurrence. Returns the id of `next`.
+    pub fn complete_and_reschedule(&self, task_id: i32, next: &Task) -> Result<i32> {
+        let tx = self.connection.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE tasks SET completed = 1, recurrence = NULL WHERE id = ?1",
+            params![task_id],
+        )?;
+        let id = insert_task_row(&tx, &self.database_id, next)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Insert the tasks imported from a calendar, skipping those with the UID of a
+    /// task in the database, imported before or exported from it. Returns how many
+    /// were inserted.
+    pub fn insert_imported_tasks(&self, tasks: &[(Option<String>, Task)]) -> Result<usize> {
+        let tx = self.connection.unchecked_transaction()?;
+        let mut inserted = 0;
+        for (uid, task) in tasks {
+            if let Some(uid) = uid {
+                let known = tx
+                    .query_row(
+                        "SELECT 1 FROM tasks WHERE uid = ?1",
+                        params![uid],
+                        |_| Ok(()),
+                    )
+                    .optional()?;
+                if known.is_some() {
+                    continue;
+                }
+            }
+            let task = Task {
+                uid: uid.clone(),
+                ..task.clone()
+            };
+            let id = insert_task_row(&tx, &self.database_id, &task)?;
+            if task.completed {
+                tx.execute("UPDATE tasks SET completed = 1 WHERE id = ?1", params![id])?;
+            }
+            inserted += 1;
         }
-        tasks
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    fn query_tasks<P: Params>(&self, sql: &str, params: P) -> Result<Vec<Task>> {
+        let mut stmt = self.connection.prepare(sql)?;
+        let mut tasks = stmt
+            .query_map(params, |row| Task::try_from(row))?
+            .collect::<rusqlite::Result<Vec<Task>>>()?;
+        self.load_tags(&mut tasks)?;
+        Ok(tasks)
     }
 
-    pub fn get_task_by_id(&self, task_id: i32) -> Result<Task> {
+    fn load_tags(&self, tasks: &mut [Task]) -> Result<()> {
         let mut stmt = self.connection.prepare(
-            "SELECT id, title, description, completed, priority, end_date FROM tasks where id = ?1",
+            "SELECT task_tags.task_id, tags.name FROM task_tags
+            JOIN tags ON tags.id = task_tags.tag_id ORDER BY tags.name",
         )?;
-        stmt.query_row(params![task_id], |row| Task::try_from(row))
-            .with_context(|| format!("Couldn't get task at index {task_id}"))
+        let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (task_id, name) = row?;
+            tags.entry(task_id).or_default().push(name);
+        }
+        for task in tasks {
+            task.tags = tags.remove(&task.id).unwrap_or_default();
+        }
+        Ok(())
     }
 
-    pub fn get_all_task_by_highest_priority(&self) -> Vec<Task> {
-        let mut stmt = self
-            .connection
-            .prepare("SELECT id, title, description, completed, priority, end_date FROM tasks order by priority asc")
-            .unwrap();
-        let task_row_iter = stmt
-            .query_map([], |row| Task::try_from(row))
-            .context("Couldn't get results from DB.")
-            .unwrap();
-        let mut tasks = Vec::new();
-        for task in task_row_iter {
-            tasks.push(task.unwrap());
-        }
-        tasks
+    pub fn get_all_tasks(&self) -> Result<Vec<Task>> {
+        self.query_tasks(SELECT_TASKS, [])
     }
 
-    pub fn get_all_task_by_lowest_priority(&self) -> Vec<Task> {
+    /// The task with `task_id`, `None` if there is no such task
+    pub fn get_task_by_id(&self, task_id: i32) -> Result<Option<Task>> {
         let mut stmt = self
             .connection
-            .prepare("SELECT id, title, description, completed, priority, end_date FROM tasks order by priority desc")
-            .unwrap();
-        let task_row_iter = stmt
-            .query_map([], |row| Task::try_from(row))
-            .context("Couldn't get results from DB.")
-            .unwrap();
-        let mut tasks = Vec::new();
-        for task in task_row_iter {
-            tasks.push(task.unwrap());
-        }
-        tasks
+            .prepare(&format!("{SELECT_TASKS} where id = ?1"))?;
+        let task = stmt
+            .query_row(params![task_id], |row| Task::try_from(row))
+            .optional()?;
+        Ok(match task {
+            Some(task) => {
+                let mut tasks = [task];
+                self.load_tags(&mut tasks)?;
+                let [task] = tasks;
+                Some(task)
+            }
+            None => None,
+        })
     }
 
-    pub fn get_all_tasks_by_newest(&self) -> Vec<Task> {
-        let mut stmt = self
+    /// Tasks tagged with `tag`, highest priority first
+    pub fn get_tasks_with_tag(&self, tag: &str) -> Result<Vec<Task>> {
+        self.query_tasks(
+            &format!(
+                "{SELECT_TASKS} where id in (SELECT task_tags.task_id FROM task_tags
+                JOIN tags ON tags.id = task_tags.tag_id WHERE tags.name = ?1)
+                order by priority asc"
+            ),
+            params![tag.trim()],
+        )
+    }
+
+    /// Tasks of the project, highest priority first
+    pub fn get_tasks_in_project(&self, project_id: i32) -> Result<Vec<Task>> {
+        self.query_tasks(
+            &format!("{SELECT_TASKS} where project_id = ?1 order by priority asc"),
+            params![project_id],
+        )
+    }
+
+    /// Every project with its number of tasks, the inbox first and then by name
+    pub fn get_all_projects(&self) -> Result<Vec<(Project, usize)>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT projects.id, projects.name, count(tasks.id) FROM projects
+            LEFT JOIN tasks ON tasks.project_id = projects.id
+            GROUP BY projects.id ORDER BY projects.id <> 1, projects.name",
+        )?;
+        let projects = stmt
+            .query_map([], |row| {
+                let project = Project {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                };
+                Ok((project, row.get(2)?))
+            })?
+            .collect::<rusqlite::Result<Vec<(Project, usize)>>>()?;
+        Ok(projects)
+    }
+
+    pub fn get_project_by_name(&self, name: &str) -> Result<Option<Project>> {
+        Ok(self
             .connection
-            .prepare("SELECT id, title, description, completed, priority, end_date FROM tasks order by end_date desc")
-            .unwrap();
-        let task_row_iter = stmt
-            .query_map([], |row| Task::try_from(row))
-            .context("Couldn't get results from DB.")
-            .unwrap();
-        let mut tasks = Vec::new();
-        for task in task_row_iter {
-            tasks.push(task.unwrap());
+            .query_row(
+                "SELECT id, name FROM projects WHERE name = ?1",
+                params![name.trim()],
+                |row| {
+                    Ok(Project {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Create a project and return its id
+    pub fn insert_project(&self, name: &str) -> Result<i32> {
+        let name = name.trim();
+        if self.get_project_by_name(name)?.is_some() {
+            return Err(TaskError::ProjectExists(name.to_string()));
         }
-        tasks
+        self.connection
+            .execute("INSERT INTO projects (name) VALUES (?1)", params![name])?;
+        Ok(self.connection.last_insert_rowid() as i32)
+    }
+
+    /// Move the task and its subtasks to the project
+    pub fn move_task_to_project(&self, task_id: i32, project_id: i32) -> Result<usize> {
+        Ok(self.connection.execute(
+            &format!("{SUBTREE} UPDATE tasks SET project_id = ?2 WHERE id IN subtree"),
+            params![task_id, project_id],
+        )?)
     }
 
-    pub fn get_all_tasks_by_oldest(&self) -> Vec<Task> {
+    /// Completed and total number of direct subtasks, by parent id
+    pub fn get_subtask_counts(&self) -> Result<HashMap<i32, (usize, usize)>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT parent_id, sum(completed), count(*) FROM tasks
+            WHERE parent_id IS NOT NULL GROUP BY parent_id",
+        )?;
+        let counts = stmt
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<rusqlite::Result<HashMap<i32, (usize, usize)>>>()?;
+        Ok(counts)
+    }
+
+    /// Names of the tags used by at least one task, sorted
+    pub fn get_all_tags(&self) -> Result<Vec<String>> {
         let mut stmt = self
             .connection
-            .prepare("SELECT id, title, description, completed, priority, end_date FROM tasks order by end_date asc")
-            .unwrap();
-        let task_row_iter = stmt
-            .query_map([], |row| Task::try_from(row))
-            .context("Couldn't get results from DB.")
-            .unwrap();
-        let mut tasks = Vec::new();
-        for task in task_row_iter {
-            tasks.push(task.unwrap());
+            .prepare("SELECT name FROM tags ORDER BY name")?;
+        let tags = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(tags)
+    }
+
+    /// Tag the task, returns 0 if it already had the tag
+    pub fn add_tag(&self, task_id: i32, tag: &str) -> Result<usize> {
+        if self.get_task_by_id(task_id)?.is_none() {
+            return Err(TaskError::NotFound(task_id));
         }
-        tasks
+        insert_tag(&self.connection, task_id, tag)
     }
 
-    pub fn toggle_task_completed(&self, task_id: i32, completed: bool) -> usize {
-        let completed = match completed {
-            true => 1,
-            false => 0,
-        };
-        self.connection
-            .execute(
-                "UPDATE tasks SET completed = ?2 WHERE id = ?1",
-                params![task_id, completed],
-            )
-            .context("Can't update the task completed property.")
-            .unwrap()
+    /// Remove a tag from the task, returns 0 if it didn't have it
+    pub fn remove_tag(&self, task_id: i32, tag: &str) -> Result<usize> {
+        let removed = self.connection.execute(
+            "DELETE FROM task_tags WHERE task_id = ?1
+            AND tag_id IN (SELECT id FROM tags WHERE name = ?2)",
+            params![task_id, tag.trim()],
+        )?;
+        self.prune_tags()?;
+        Ok(removed)
     }
 
-    pub fn update_task_priority(&self, task_id: i32, priority: Priority) -> usize {
-        self.connection
-            .execute(
-                "UPDATE tasks SET priority = ?2 WHERE id = ?1",
-                params![task_id, priority as u8],
-            )
-            .context("Can't update the task priority property.")
-            .unwrap()
+    // Tags nobody uses anymore would show up in the tag filter forever.
+    fn prune_tags(&self) -> Result<()> {
+        self.connection.execute(
+            "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM task_tags)",
+            [],
+        )?;
+        Ok(())
     }
 
-    pub fn update_task(&self, task: &Task) -> usize {
-        self.connection
-            .execute(
-                "UPDATE tasks SET title = ?2, description = ?3, end_date=?4 WHERE id = ?1",
-                params![task.id, task.title, task.description, task.date],
-            )
-            .context("Can't update the task.")
-            .unwrap()
+    pub fn get_all_task_by_highest_priority(&self) -> Result<Vec<Task>> {
+        self.query_tasks(&format!("{SELECT_TASKS} order by priority asc"), [])
     }
 
-    pub fn delete_task(&self, task_id: i32) -> usize {
-        self.connection
-            .execute("delete from tasks where id = ?1", params![task_id])
-            .context("Can't delete the task.")
-            .unwrap()
+    pub fn get_all_task_by_lowest_priority(&self) -> Result<Vec<Task>> {
+        self.query_tasks(&format!("{SELECT_TASKS} order by priority desc"), [])
+    }
+
+    pub fn get_all_tasks_by_newest(&self) -> Result<Vec<Task>> {
+        self.query_tasks(
+            &format!("{SELECT_TASKS} order by end_date desc nulls last, due_time desc nulls first"),
+            [],
+        )
+    }
+
+    pub fn get_all_tasks_by_oldest(&self) -> Result<Vec<Task>> {
+        self.query_tasks(
+            &format!("{SELECT_TASKS} order by end_date asc nulls last, due_time asc nulls last"),
+            [],
+        )
+    }
+
+    pub fn toggle_task_completed(&self, task_id: i32, completed: bool) -> Result<usize> {
+        Ok(self.connection.execute(
+            "UPDATE tasks SET completed = ?2 WHERE id = ?1",
+            params![task_id, completed],
+        )?)
+    }
+
+    /// Set the completed status of the task and of all its subtasks
+    pub fn set_subtree_completed(&self, task_id: i32, completed: bool) -> Result<usize> {
+        Ok(self.connection.execute(
+            &format!("{SUBTREE} UPDATE tasks SET completed = ?2 WHERE id IN subtree"),
+            params![task_id, completed],
+        )?)
+    }
+
+    pub fn update_task_priority(&self, task_id: i32, priority: Priority) -> Result<usize> {
+        Ok(self.connection.execute(
+            "UPDATE tasks SET priority = ?2 WHERE id = ?1",
+            params![task_id, priority.to_u8()],
+        )?)
+    }
+
+    /// Update title, description, due date and time, recurrence and tags of the task
+    pub fn update_task(&self, task: &Task) -> Result<usize> {
+        let tx = self.connection.unchecked_transaction()?;
+        let (due_time, timezone) = due_time_to_sql(task.due_time.as_ref());
+        let updated = tx.execute(
+            "UPDATE tasks SET title = ?2, description = ?3, end_date=?4, recurrence = ?5, due_time = ?6, timezone = ?7 WHERE id = ?1",
+            params![
+                task.id,
+                task.title,
+                task.description,
+                task.date,
+                task.recurrence,
+                due_time,
+                timezone,
+            ],
+        )?;
+        if updated > 0 {
+            tx.execute("DELETE FROM task_tags WHERE task_id = ?1", params![task.id])?;
+            for tag in &task.tags {
+                insert_tag(&tx, task.id, tag)?;
+            }
+        }
+        tx.commit()?;
+        self.prune_tags()?;
+        Ok(updated)
+    }
+
+    pub fn delete_task(&self, task_id: i32) -> Result<usize> {
+        let deleted = self
+            .connection
+            .execute("delete from tasks where id = ?1", params![task_id])?;
+        self.prune_tags()?;
+        Ok(deleted)
     }
 
-    pub fn get_record_count(&self) -> i64 {
+    pub fn get_record_count(&self) -> Result<i64> {
         let query = "SELECT count(*) FROM tasks";
-        self.connection
-            .query_row(query, [], |r| r.get(0))
-            .context("Can't get record count")
-            .unwrap()
+        Ok(self.connection.query_row(query, [], |r| r.get(0))?)
     }
 
-    pub fn clear(&self) -> usize {
-        self.connection
-            .execute("DELETE FROM tasks", [])
-            .context("Can't clear database")
-            .unwrap()
+    pub fn clear(&self) -> Result<usize> {
+        let deleted = self.connection.execute("DELETE FROM tasks", [])?;
+        self.prune_tags()?;
+        Ok(deleted)
+    }
+}
+
+// Insert the task with its tags and return its id, within the caller's transaction.
+// Insert the task with its own UID, or else the one made from its new id
+fn insert_task_row(conn: &Connection, database_id: &str, task: &Task) -> Result<i32> {
+    let (due_time, timezone) = due_time_to_sql(task.due_time.as_ref());
+    conn.execute(
+        "INSERT INTO tasks (title, description, completed, priority, end_date, project_id, parent_id, recurrence, due_time, timezone) VALUES (?1, ?2, 0, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            task.title.trim(),
+            task.description.trim(),
+            task.priority.to_u8(),
+            task.date,
+            task.project_id,
+            task.parent_id,
+            task.recurrence,
+            due_time,
+            timezone,
+        ],
+    )?;
+    let id = conn.last_insert_rowid() as i32;
+    let uid = task.uid.clone().unwrap_or_else(|| task_uid(database_id, id));
+    conn.execute("UPDATE tasks SET uid = ?2 WHERE id = ?1", params![id, uid])?;
+    for tag in &task.tags {
+        insert_tag(conn, id, tag)?;
+    }
+    Ok(id)
+}
+
+// Link the task to the tag named `name`, creating the tag if needed.
+fn insert_tag(conn: &Connection, task_id: i32, name: &str) -> Result<usize> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(0);
     }
+    conn.execute(
+        "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+        params![name],
+    )?;
+    Ok(conn.execute(
+        "INSERT OR IGNORE INTO task_tags (task_id, tag_id) SELECT ?1, id FROM tags WHERE name = ?2",
+        params![task_id, name],
+    )?)
 }
 
 impl TryFrom<&Row<'_>> for Task {
     type Error = rusqlite::Error;
 
     fn try_from(row: &Row<'_>) -> rusqlite::Result<Self, Self::Error> {
+        let priority: u8 = row.get(4)?;
         Ok(Task {
             id: row.get(0)?,
             title: row.get(1)?,
             description: row.get(2)?,
             completed: row.get(3)?,
-            priority: Priority::from_u8(row.get(4)?).expect("Invalid priority"),
-            date: TaskDate::column_result(row.get_ref(5)?).unwrap_or(TaskDate(None)),
+            priority: Priority::from_u8(priority).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    4,
+                    Type::Integer,
+                    format!("invalid priority {priority}").into(),
+                )
+            })?,
+            date: row.get(5)?,
+            tags: Vec::new(),
+            project_id: row.get(6)?,
+            parent_id: row.get(7)?,
+            recurrence: row.get(8)?,
+            due_time: due_time_from_row(row, 9, 10)?,
+            uid: row.get(11)?,
         })
     }
 }
 
+// The time and timezone columns of `due_time`
+fn due_time_to_sql(due_time: Option<&DueTime>) -> (Option<String>, Option<&'static str>) {
+    (
+        due_time.map(|d| d.time.format(TIME_FORMAT).to_string()),
+        due_time.and_then(|d| d.timezone).map(|tz| tz.name()),
+    )
+}
+
+fn due_time_from_row(
+    row: &Row<'_>,
+    time_idx: usize,
+    tz_idx: usize,
+) -> rusqlite::Result<Option<DueTime>> {
+    let Some(time) = row.get::<_, Option<String>>(time_idx)? else {
+        return Ok(None);
+    };
+    let time = NaiveTime::parse_from_str(&time, TIME_FORMAT)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(time_idx, Type::Text, e.into()))?;
+    let timezone = match row.get::<_, Option<String>>(tz_idx)? {
+        Some(tz) => Some(tz.parse::<Tz>().map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(tz_idx, Type::Text, e.to_string().into())
+        })?),
+        None => None,
+    };
+    Ok(Some(DueTime { time, timezone }))
+}
+
 impl FromSql for TaskDate {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
         match value {
             ValueRef::Null => Ok(TaskDate(None)),
-            ValueRef::Text(text) => {
-                let date_str = std::str::from_utf8(text).unwrap();
-                let date = NaiveDate::parse_from_str(date_str, DATE_FORMAT).unwrap();
+            ValueRef::Text(_) => {
+                let date = NaiveDate::parse_from_str(value.as_str()?, STORAGE_DATE_FORMAT)
+                    .map_err(|e| FromSqlError::Other(Box::new(e)))?;
                 Ok(TaskDate(Some(date)))
             }
-            _ => Err(rusqlite::types::FromSqlError::InvalidType.into()),
+            _ => Err(FromSqlError::InvalidType),
         }
     }
 }
 
+impl FromSql for Recurrence {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+impl ToSql for Recurrence {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
 impl ToSql for TaskDate {
     fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
         match self.0 {
-            Some(date) => Ok(ToSqlOutput::from(date.format(DATE_FORMAT).to_string())),
+            Some(date) => Ok(ToSqlOutput::from(
+                date.format(STORAGE_DATE_FORMAT).to_string(),
+            )),
             None => Ok(ToSqlOutput::from(rusqlite::types::Null)),
         }
     }
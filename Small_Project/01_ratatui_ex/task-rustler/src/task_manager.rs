@@ -1,5 +1,9 @@
+use crate::error::{Result, TaskError};
+use crate::project::Project;
 use crate::task::{Priority, Task};
 use crate::task_db::DB;
+use chrono::NaiveDate;
+use std::collections::HashMap;
 
 #[derive(Debug, Copy, Clone)]
 pub enum SortOrder {
@@ -15,8 +19,10 @@ pub struct TasksService {
 }
 
 impl Default for TasksService {
+    /// An empty in-memory database
     fn default() -> Self {
-        Self::new(String::new())
+        // Opening and migrating an empty in-memory database only fails if SQLite itself is broken.
+        Self::new(String::new()).expect("Can't create an in-memory database")
     }
 }
 
@@ -25,82 +31,173 @@ impl TasksService {
     /// `db_path` is the path to the database file. If it doesn't exist it is going to be created.
     /// If db_path is an empty string __""__, an in memory instance of database is going to be
     /// created instead.
-    pub fn new(db_path: String) -> Self {
-        Self {
-            db: DB::create_and_return_connection(db_path.as_str()),
+    /// The schema of an existing database is upgraded to the current version.
+    pub fn new(db_path: String) -> Result<Self> {
+        Ok(Self {
+            db: DB::create_and_return_connection(db_path.as_str())?,
             db_filename: db_path,
-        }
+        })
+    }
+
+    /// Add `task` and return the id it was given
+    pub fn add_new_task(&self, task: &Task) -> Result<i32> {
+        self.db.insert_task(task)
+    }
+
+    /// Add `task` as a step of the task with `parent_id` and return its id.
+    /// The subtask goes in the project of its parent.
+    pub fn add_subtask(&self, parent_id: i32, task: &Task) -> Result<i32> {
+        let parent = self
+            .db
+            .get_task_by_id(parent_id)?
+            .ok_or(TaskError::NotFound(parent_id))?;
+        let subtask = Task {
+            parent_id: Some(parent_id),
+            project_id: parent.project_id,
+            ..task.clone()
+        };
+        self.db.insert_task(&subtask)
     }
 
-    pub fn add_new_task(&self, task: &Task) {
-        self.db.insert_task(task);
+    /// Add the tasks read by `import_tasks_from_icalendar`, those already imported
+    /// are recognised by their UID and skipped. Returns how many were added.
+    pub fn import_tasks(&self, tasks: &[(Option<String>, Task)]) -> Result<usize> {
+        self.db.insert_imported_tasks(tasks)
+    }
+
+    /// Random id of the database, exported calendars need it for the UIDs of the tasks
+    pub fn database_id(&self) -> &str {
+        self.db.database_id()
     }
 
     /// Get a task with `task_id`. Returns an Option containing the task or None
     /// if it couldn't find the task.
-    pub fn get_task(&self, task_id: i32) -> Option<Task> {
-        match self.db.get_task_by_id(task_id) {
-            Ok(t) => Some(t),
-            Err(e) => {
-                eprintln!("{e}");
-                if let Some(cause) = e.source() {
-                    eprintln!("Underlying cause: {:?}", cause);
-                }
-                None
-            }
-        }
+    pub fn get_task(&self, task_id: i32) -> Result<Option<Task>> {
+        self.db.get_task_by_id(task_id)
     }
 
     /// Returns all the tasks present in the database
-    pub fn get_all_tasks(&self) -> Vec<Task> {
+    pub fn get_all_tasks(&self) -> Result<Vec<Task>> {
         self.db.get_all_tasks()
     }
 
     /// Return all the tasks sorted by `sort`
-    pub fn get_all_tasks_sorted_by_priority(&self, sort: SortOrder) -> Vec<Task> {
+    pub fn get_all_tasks_sorted_by_priority(&self, sort: SortOrder) -> Result<Vec<Task>> {
         match sort {
             SortOrder::High => self.db.get_all_task_by_highest_priority(),
             SortOrder::Low => self.db.get_all_task_by_lowest_priority(),
         }
     }
 
-    pub fn get_all_tasks_sorted_by_date(&self, sort: SortOrder) -> Vec<Task> {
+    pub fn get_all_tasks_sorted_by_date(&self, sort: SortOrder) -> Result<Vec<Task>> {
         match sort {
             SortOrder::High => self.db.get_all_tasks_by_newest(),
             SortOrder::Low => self.db.get_all_tasks_by_oldest(),
         }
     }
 
-    pub fn toggle_task_status(&self, task_id: i32, completed: bool) -> usize {
+    pub fn toggle_task_status(&self, task_id: i32, completed: bool) -> Result<usize> {
         self.db.toggle_task_completed(task_id, completed)
     }
 
+    /// Mark the task as completed. A recurring task is followed by its next
+    /// occurrence, whose id is returned, see `Task::next_occurrence`.
+    pub fn complete_task(&self, task_id: i32, today: NaiveDate) -> Result<Option<i32>> {
+        let task = self
+            .db
+            .get_task_by_id(task_id)?
+            .ok_or(TaskError::NotFound(task_id))?;
+        match task.next_occurrence(today) {
+            Some(next) => Ok(Some(self.db.complete_and_reschedule(task_id, &next)?)),
+            None => {
+                self.db.toggle_task_completed(task_id, true)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Set the status of the task together with all its subtasks
+    pub fn set_status_with_subtasks(&self, task_id: i32, completed: bool) -> Result<usize> {
+        self.db.set_subtree_completed(task_id, completed)
+    }
+
+    /// Completed and total number of direct subtasks of every task that has some
+    pub fn subtask_counts(&self) -> Result<HashMap<i32, (usize, usize)>> {
+        self.db.get_subtask_counts()
+    }
+
     /// Change priority of the task
-    pub fn change_priority(&self, task_id: i32, priority: &Priority) -> usize {
+    pub fn change_priority(&self, task_id: i32, priority: &Priority) -> Result<usize> {
         self.db.update_task_priority(task_id, priority.to_owned())
     }
 
-    pub fn update_task(&self, task: &Task) {
-        self.db.update_task(task);
+    pub fn update_task(&self, task: &Task) -> Result<usize> {
+        self.db.update_task(task)
+    }
+
+    /// Tag a task, returns 0 if it already had the tag
+    pub fn add_tag(&self, task_id: i32, tag: &str) -> Result<usize> {
+        self.db.add_tag(task_id, tag)
+    }
+
+    /// Remove a tag from a task, returns 0 if it didn't have it
+    pub fn remove_tag(&self, task_id: i32, tag: &str) -> Result<usize> {
+        self.db.remove_tag(task_id, tag)
+    }
+
+    /// All the tasks tagged with `tag`, highest priority first
+    pub fn tasks_with_tag(&self, tag: &str) -> Result<Vec<Task>> {
+        self.db.get_tasks_with_tag(tag)
+    }
+
+    /// Every tag in use, sorted by name
+    pub fn all_tags(&self) -> Result<Vec<String>> {
+        self.db.get_all_tags()
+    }
+
+    /// Create a new project and return its id
+    pub fn add_project(&self, name: &str) -> Result<i32> {
+        self.db.insert_project(name)
+    }
+
+    /// All the projects with how many tasks they have, the inbox first
+    pub fn projects(&self) -> Result<Vec<(Project, usize)>> {
+        self.db.get_all_projects()
+    }
+
+    pub fn project_by_name(&self, name: &str) -> Result<Project> {
+        self.db
+            .get_project_by_name(name)?
+            .ok_or_else(|| TaskError::ProjectNotFound(name.trim().to_string()))
+    }
+
+    /// Tasks of the project, highest priority first
+    pub fn tasks_in_project(&self, project_id: i32) -> Result<Vec<Task>> {
+        self.db.get_tasks_in_project(project_id)
+    }
+
+    /// Move the task, with its subtasks, to another project
+    pub fn move_task(&self, task_id: i32, project_id: i32) -> Result<usize> {
+        self.db.move_task_to_project(task_id, project_id)
     }
 
-    /// Delete a task with `task_id` number
-    pub fn delete_task(&self, task_id: i32) -> usize {
+    /// Delete a task with `task_id` number, its subtasks are deleted too
+    pub fn delete_task(&self, task_id: i32) -> Result<usize> {
         self.db.delete_task(task_id)
     }
 
     /// Number of tasks present in the database
-    pub fn length(&self) -> i64 {
+    pub fn length(&self) -> Result<i64> {
         self.db.get_record_count()
     }
 
     /// Check if there are no tasks at all
-    pub fn is_empty(&self) -> bool {
-        self.length() == 0
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.length()? == 0)
     }
 
     /// Clean the database, delete all tasks
-    pub fn delete_all_tasks(&self) -> usize {
+    pub fn delete_all_tasks(&self) -> Result<usize> {
         self.db.clear()
     }
 }
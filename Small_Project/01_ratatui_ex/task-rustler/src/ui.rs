@@ -1,10 +1,14 @@
 use crate::app::{AppContext, InputFieldType, InputMode};
+use crate::date::{format_due, parse_due};
 use crate::task::{Priority, Task};
 use ratatui::layout::{Constraint, Flex, Layout, Position, Rect};
 use ratatui::prelude::{Color, Direction, Line, Modifier, Span, StatefulWidget, Style};
 use ratatui::style::palette::tailwind::{BLUE, SLATE};
 use ratatui::style::Stylize;
-use ratatui::widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, Paragraph};
+use ratatui::widgets::{
+    Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph,
+};
+use chrono::Local;
 use ratatui::{symbols, Frame};
 use std::vec;
 const TODO_HEADER_STYLE: Style = Style::new().fg(SLATE.c100).bg(BLUE.c800);
@@ -12,39 +16,51 @@ const NORMAL_ROW_BG: Color = SLATE.c950;
 const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
 const TEXT_FG_COLOR: Color = SLATE.c200;
 const COMPLETED_TEXT_FG_COLOR: Color = SLATE.c500;
+const SIDEBAR_WIDTH: u16 = 24;
 
 pub fn ui(f: &mut Frame, app: &mut AppContext) {
+    let [sidebar_area, area] =
+        Layout::horizontal([Constraint::Length(SIDEBAR_WIDTH), Constraint::Min(1)])
+            .areas(f.area());
+    render_projects_sidebar(f, app, sidebar_area);
     match app.input_mode {
-        InputMode::View => {
+        InputMode::View | InputMode::MoveTask | InputMode::ConfirmCompleteSubtasks => {
             let [main_area, message_area] =
                 Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
                     .margin(1)
-                    .areas(f.area());
+                    .areas(area);
             render_list(f, app, main_area);
             render_message_area(f, app, message_area);
         }
         InputMode::Adding | InputMode::EditingExisting => {
-            let [main_area, input_title_area, input_description_area, input_date_area, message_area] =
+            let [main_area, input_title_area, input_description_area, input_date_area, date_preview_area, input_tags_area, input_recurrence_area, message_area] =
                 Layout::vertical([
                     Constraint::Min(1),
                     Constraint::Length(3),
                     Constraint::Length(3),
                     Constraint::Length(3),
                     Constraint::Length(1),
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(1),
                 ])
                     .margin(1)
-                    .areas(f.area());
+                    .areas(area);
 
             let input_area = match app.input_field {
                 InputFieldType::Title => input_title_area,
                 InputFieldType::Description => input_description_area,
                 InputFieldType::Date => input_date_area,
+                InputFieldType::Tags => input_tags_area,
+                InputFieldType::Recurrence => input_recurrence_area,
             };
             let x = input_area.x
                 + match app.input_field {
                 InputFieldType::Title => app.input_title.len() as u16,
                 InputFieldType::Description => app.input_description.len() as u16,
                 InputFieldType::Date => app.input_date.len() as u16,
+                InputFieldType::Tags => app.input_tags.len() as u16,
+                InputFieldType::Recurrence => app.input_recurrence.len() as u16,
             }
                 + 1;
             let y = input_area.y + 1;
@@ -54,6 +70,9 @@ pub fn ui(f: &mut Frame, app: &mut AppContext) {
             render_input_title_area(f, app, input_title_area);
             render_input_description_area(f, app, input_description_area);
             render_input_date_area(f, app, input_date_area);
+            render_date_preview(f, app, date_preview_area);
+            render_input_tags_area(f, app, input_tags_area);
+            render_input_recurrence_area(f, app, input_recurrence_area);
             render_message_area(f, app, message_area);
         }
         InputMode::Export => {
@@ -63,7 +82,7 @@ pub fn ui(f: &mut Frame, app: &mut AppContext) {
                 Constraint::Length(1),
             ])
                 .margin(1)
-                .areas(f.area());
+                .areas(area);
             let x = input_area.x + app.input_export_path.len() as u16 +1;
             let y = input_area.y + 1;
             f.set_cursor_position(Position::new(x, y));
@@ -71,6 +90,36 @@ pub fn ui(f: &mut Frame, app: &mut AppContext) {
             render_input_path_area(f, app, input_area);
             render_message_area(f, app, message_area);
         }
+        InputMode::Import => {
+            let [main_area, input_area, message_area] = Layout::vertical([
+                Constraint::Min(1),
+                Constraint::Length(3),
+                Constraint::Length(1),
+            ])
+                .margin(1)
+                .areas(area);
+            let x = input_area.x + app.input_import_path.len() as u16 + 1;
+            let y = input_area.y + 1;
+            f.set_cursor_position(Position::new(x, y));
+            render_list(f, app, main_area);
+            render_input_import_path_area(f, app, input_area);
+            render_message_area(f, app, message_area);
+        }
+        InputMode::NewProject => {
+            let [main_area, input_area, message_area] = Layout::vertical([
+                Constraint::Min(1),
+                Constraint::Length(3),
+                Constraint::Length(1),
+            ])
+                .margin(1)
+                .areas(area);
+            let x = input_area.x + app.input_project_name.len() as u16 + 1;
+            let y = input_area.y + 1;
+            f.set_cursor_position(Position::new(x, y));
+            render_list(f, app, main_area);
+            render_input_project_name_area(f, app, input_area);
+            render_message_area(f, app, message_area);
+        }
     }
 
     if app.show_help {
@@ -86,23 +135,70 @@ pub fn ui(f: &mut Frame, app: &mut AppContext) {
             .split(area);
         let t1 = Paragraph::new(vec![
             Line::raw("'a' to add a task"),
+            Line::raw("'Shift + a' to add a subtask to the selected task"),
+            Line::raw("'c' / 'e' to collapse / expand the subtasks"),
             Line::raw("'m' to modify the selected task"),
             Line::raw("'p' to change the priority"),
             Line::raw("'s' to sort by priority"),
             Line::raw("'t' to sort by date"),
+            Line::raw("'f' to filter by the next tag"),
+            Line::raw("'←→' to switch project"),
+            Line::raw("'n' to create a project"),
+            Line::raw("'v' to move the selected task to another project"),
             Line::raw("'↑↓' to select task"),
             Line::raw("'Space' to toggle status"),
             Line::raw("'Ctrl + d' to delete the selected task"),
             Line::raw("'Ctrl + e' to export the tasks to .ics file"),
+            Line::raw("'Ctrl + o' to import the tasks of an .ics file"),
             Line::raw("'Ctrl + q' to quit"),
         ]);
         f.render_widget(t1, popup_chunks[0]);
     }
 }
 
+/// Project names with their task count. While moving a task the highlighted
+/// project is the destination, otherwise it's the one being viewed.
+fn render_projects_sidebar(f: &mut Frame, app: &AppContext, area: Rect) {
+    let total: usize = app.projects.iter().map(|(_, count)| count).sum();
+    let mut items = vec![ListItem::new(format!("All ({total})"))];
+    items.extend(
+        app.projects
+            .iter()
+            .map(|(project, count)| ListItem::new(format!("{} ({count})", project.name))),
+    );
+    let (title, selected) = match app.input_mode {
+        InputMode::MoveTask => ("Move to", app.move_target + 1),
+        _ => (
+            "Projects",
+            app.project_filter
+                .and_then(|id| app.projects.iter().position(|(p, _)| p.id == id))
+                .map_or(0, |i| i + 1),
+        ),
+    };
+    let block = Block::new()
+        .title(Line::raw(title).centered())
+        .borders(Borders::TOP)
+        .border_set(symbols::border::EMPTY)
+        .border_style(TODO_HEADER_STYLE)
+        .bg(NORMAL_ROW_BG);
+    let list = List::new(items)
+        .block(block)
+        .fg(TEXT_FG_COLOR)
+        .highlight_style(SELECTED_STYLE)
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+    let mut state = ListState::default().with_selected(Some(selected));
+    StatefulWidget::render(list, area, f.buffer_mut(), &mut state);
+}
+
 fn render_list(f: &mut Frame, app: &mut AppContext, area: Rect) {
+    let project = app.current_project().map_or("Task Rustler", |p| p.name.as_str());
+    let title = match &app.tag_filter {
+        Some(tag) => format!("{project} #{tag}"),
+        None => project.to_string(),
+    };
     let block = Block::new()
-        .title(Line::raw("Task Rustler").centered())
+        .title(Line::raw(title).centered())
         .borders(Borders::TOP)
         .border_set(symbols::border::EMPTY)
         .border_style(TODO_HEADER_STYLE)
@@ -112,8 +208,7 @@ fn render_list(f: &mut Frame, app: &mut AppContext, area: Rect) {
         .task_list
         .items
         .iter()
-        .enumerate()
-        .map(|(_, item)| ListItem::from(item))
+        .map(|item| task_list_item(item, app))
         .collect();
 
     let list = List::new(items)
@@ -136,7 +231,44 @@ fn render_input_description_area(f: &mut Frame, app: &mut AppContext, area: Rect
 }
 
 fn render_input_date_area(f: &mut Frame, app: &mut AppContext, area: Rect) {
-    let input = create_input_paragraph(app, app.input_date.as_str(), "Date (dd-mm-yyyy)");
+    let input = create_input_paragraph(
+        app,
+        app.input_date.as_str(),
+        "Date (dd-mm-yyyy, today, fri, next mon, +3d, eom [HH:MM [timezone]])",
+    );
+    f.render_widget(input, area);
+}
+
+// The date being typed as it will be saved, so relative dates can be checked
+fn render_date_preview(f: &mut Frame, app: &AppContext, area: Rect) {
+    if app.input_date.trim().is_empty() {
+        return;
+    }
+    let preview = match parse_due(&app.input_date, Local::now().date_naive()) {
+        Ok((date, due_time)) => Span::styled(
+            format!(
+                "→ {} {}",
+                date.0.map(|d| d.format("%a").to_string()).unwrap_or_default(),
+                format_due(&date, due_time.as_ref())
+            ),
+            Style::default().fg(TEXT_FG_COLOR),
+        ),
+        Err(e) => Span::styled(format!("→ {e}"), Style::default().fg(COMPLETED_TEXT_FG_COLOR)),
+    };
+    f.render_widget(Paragraph::new(Line::from(preview)), area);
+}
+
+fn render_input_tags_area(f: &mut Frame, app: &mut AppContext, area: Rect) {
+    let input = create_input_paragraph(app, app.input_tags.as_str(), "Tags (comma separated)");
+    f.render_widget(input, area);
+}
+
+fn render_input_recurrence_area(f: &mut Frame, app: &mut AppContext, area: Rect) {
+    let input = create_input_paragraph(
+        app,
+        app.input_recurrence.as_str(),
+        "Repeat (daily, weekly mon,fri, monthly 15, every 3 days)",
+    );
     f.render_widget(input, area);
 }
 
@@ -145,21 +277,44 @@ fn render_input_path_area(f: &mut Frame, app: &mut AppContext, area: Rect) {
     f.render_widget(input, area);
 }
 
+fn render_input_import_path_area(f: &mut Frame, app: &mut AppContext, area: Rect) {
+    let input = create_input_paragraph(app, app.input_import_path.as_str(), "File path");
+    f.render_widget(input, area);
+}
+
+fn render_input_project_name_area(f: &mut Frame, app: &mut AppContext, area: Rect) {
+    let input = create_input_paragraph(app, app.input_project_name.as_str(), "Project name");
+    f.render_widget(input, area);
+}
+
 fn render_message_area(f: &mut Frame, app: &mut AppContext, area: Rect) {
     let (msg, style) = match app.input_mode {
         InputMode::View => (
-            vec![
-                Span::styled("Tasks list", Style::default().bg(Color::White).fg(Color::Black)),
-                Span::raw("  Press "),
-                Span::styled("h", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" for help "),
-            ],
+            if app.error.is_none() {
+                vec![
+                    Span::styled("Tasks list", Style::default().bg(Color::White).fg(Color::Black)),
+                    Span::raw("  Press "),
+                    Span::styled("h", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" for help "),
+                ]
+            } else {
+                vec![Span::styled("Error", Style::default().bg(Color::White).fg(Color::Black)),
+                     Span::raw(" "),
+                     Span::styled(
+                         app.error.clone().unwrap_or(String::new()),
+                         Style::default().red(),
+                     )]
+            },
             Style::default().add_modifier(Modifier::BOLD),
         ),
         InputMode::Adding => (
             if app.error.is_none() {
+                let label = match app.new_task_parent {
+                    Some(_) => "Add subtask",
+                    None => "Add task",
+                };
                 vec![
-                    Span::styled("Add task", Style::default().bg(Color::White).fg(Color::Black)),
+                    Span::styled(label, Style::default().bg(Color::White).fg(Color::Black)),
                     Span::raw("  Press "),
                     Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw(" to stop editing, "),
@@ -216,6 +371,81 @@ fn render_message_area(f: &mut Frame, app: &mut AppContext, area: Rect) {
             },
             Style::default(),
         ),
+        InputMode::Import => (
+            if app.error.is_none() {
+                vec![
+                    Span::styled("Import tasks", Style::default().bg(Color::White).fg(Color::Black)),
+                    Span::raw("  Press "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to cancel, "),
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to read the file"),
+                ]
+            } else {
+                vec![Span::styled("Error", Style::default().bg(Color::White).fg(Color::Black)),
+                     Span::raw(" "),
+                     Span::styled(
+                         app.error.clone().unwrap_or_default(),
+                         Style::default().red(),
+                     )]
+            },
+            Style::default(),
+        ),
+        InputMode::NewProject => (
+            if app.error.is_none() {
+                vec![
+                    Span::styled("New project", Style::default().bg(Color::White).fg(Color::Black)),
+                    Span::raw("  Press "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to cancel, "),
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to create it"),
+                ]
+            } else {
+                vec![Span::styled("Error", Style::default().bg(Color::White).fg(Color::Black)),
+                     Span::raw(" "),
+                     Span::styled(
+                         app.error.clone().unwrap_or_default(),
+                         Style::default().red(),
+                     )]
+            },
+            Style::default(),
+        ),
+        InputMode::MoveTask => (
+            if app.error.is_none() {
+                vec![
+                    Span::styled("Move task", Style::default().bg(Color::White).fg(Color::Black)),
+                    Span::raw("  Press "),
+                    Span::styled("↑↓", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to pick a project, "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to cancel, "),
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to move it"),
+                ]
+            } else {
+                vec![Span::styled("Error", Style::default().bg(Color::White).fg(Color::Black)),
+                     Span::raw(" "),
+                     Span::styled(
+                         app.error.clone().unwrap_or_default(),
+                         Style::default().red(),
+                     )]
+            },
+            Style::default(),
+        ),
+        InputMode::ConfirmCompleteSubtasks => (
+            vec![
+                Span::styled("Complete subtasks too?", Style::default().bg(Color::White).fg(Color::Black)),
+                Span::raw("  Press "),
+                Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to complete them, "),
+                Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to complete only this task, "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to cancel"),
+            ],
+            Style::default(),
+        ),
     };
     let help_message = Paragraph::new(Line::from(msg)).style(style);
     f.render_widget(help_message, area);
@@ -229,58 +459,96 @@ fn render_popup(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
     area
 }
 
-impl From<&Task> for ListItem<'_> {
-    fn from(value: &Task) -> Self {
-        let todo_line = vec![
-            Span::styled(" ☐", Style::default().fg(TEXT_FG_COLOR)),
-            Span::styled(
-                format!(" ({})", value.priority),
-                Style::default().fg(priority_to_color(&value.priority)),
-            ),
-            Span::styled(
-                format!(
-                    "{:>14}",
-                    value
-                        .date
-                        .clone()
-                        .try_into()
-                        .unwrap_or(format!("{}", " ".repeat(10)))
-                ),
-                Style::default().fg(TEXT_FG_COLOR),
-            ),
-            Span::styled(
-                format!("    {} - {}", value.title, value.description),
-                Style::default().fg(TEXT_FG_COLOR),
-            ),
-        ];
-        let done_line = vec![
-            Span::styled(" ✓", Style::default().fg(COMPLETED_TEXT_FG_COLOR)),
-            Span::styled(
-                format!(" ({})", value.priority),
-                Style::default().fg(priority_to_color(&value.priority)),
-            ),
-            Span::styled(
-                format!(
-                    "{:>14}",
-                    value
-                        .date
-                        .clone()
-                        .try_into()
-                        .unwrap_or(format!("{}", " ".repeat(10)))
-                ),
-                Style::default().fg(COMPLETED_TEXT_FG_COLOR),
+/// The task line, indented under its parent. A task with subtasks shows whether
+/// they are collapsed and how many of them are completed.
+fn task_list_item(value: &Task, app: &AppContext) -> ListItem<'static> {
+    let depth = app.depths.get(&value.id).copied().unwrap_or(0);
+    let subtasks = app.subtask_counts.get(&value.id);
+    let fold = match subtasks {
+        Some(_) if app.collapsed.contains(&value.id) => "▸",
+        Some(_) => "▾",
+        None => " ",
+    };
+    let tree_prefix = Span::styled(
+        format!("{}{fold}", "  ".repeat(depth)),
+        Style::default().fg(TEXT_FG_COLOR),
+    );
+    let todo_line = vec![
+        Span::styled(" ☐", Style::default().fg(TEXT_FG_COLOR)),
+        Span::styled(
+            format!(" ({})", value.priority),
+            Style::default().fg(priority_to_color(&value.priority)),
+        ),
+        Span::styled(
+            format!(
+                "{:>17}",
+                format_due(&value.date, value.due_time.as_ref())
             ),
-            Span::styled(
-                format!("    {} - {}", value.title, value.description),
-                Style::default().fg(COMPLETED_TEXT_FG_COLOR),
+            Style::default().fg(TEXT_FG_COLOR),
+        ),
+        Span::styled(
+            format!("    {} - {}", value.title, value.description),
+            Style::default().fg(TEXT_FG_COLOR),
+        ),
+    ];
+    let done_line = vec![
+        Span::styled(" ✓", Style::default().fg(COMPLETED_TEXT_FG_COLOR)),
+        Span::styled(
+            format!(" ({})", value.priority),
+            Style::default().fg(priority_to_color(&value.priority)),
+        ),
+        Span::styled(
+            format!(
+                "{:>17}",
+                format_due(&value.date, value.due_time.as_ref())
             ),
-        ];
-        let line: Line = match value.completed {
-            false => todo_line.into(),
-            true => done_line.into(),
-        };
-        ListItem::new(line)
+            Style::default().fg(COMPLETED_TEXT_FG_COLOR),
+        ),
+        Span::styled(
+            format!("    {} - {}", value.title, value.description),
+            Style::default().fg(COMPLETED_TEXT_FG_COLOR),
+        ),
+    ];
+    let mut line = match value.completed {
+        false => todo_line,
+        true => done_line,
+    };
+    line.insert(0, tree_prefix);
+    if let Some(recurrence) = &value.recurrence {
+        line.push(Span::styled(
+            format!(" ↻ {recurrence}"),
+            Style::default().fg(COMPLETED_TEXT_FG_COLOR),
+        ));
+    }
+    if let Some((done, total)) = subtasks {
+        let color = if done == total { Color::Green } else { Color::Yellow };
+        line.push(Span::styled(format!(" {done}/{total}"), Style::default().fg(color)));
     }
+    line.extend(tag_spans(&value.tags));
+    ListItem::new(Line::from(line))
+}
+
+/// One colored `#tag` span per tag, a tag keeps its color between runs
+fn tag_spans(tags: &[String]) -> Vec<Span<'static>> {
+    tags.iter()
+        .map(|tag| Span::styled(format!(" #{tag}"), Style::default().fg(tag_color(tag))))
+        .collect()
+}
+
+fn tag_color(tag: &str) -> Color {
+    const TAG_COLORS: [Color; 6] = [
+        Color::Cyan,
+        Color::Magenta,
+        Color::LightBlue,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightMagenta,
+    ];
+    let hash = tag
+        .to_lowercase()
+        .bytes()
+        .fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+    TAG_COLORS[hash % TAG_COLORS.len()]
 }
 
 fn priority_to_color(priority: &Priority) -> Color {
@@ -294,7 +562,12 @@ fn priority_to_color(priority: &Priority) -> Color {
 fn create_input_paragraph<'a>(app: &'a AppContext, text: &'a str, title: &'a str) -> Paragraph<'a> {
     Paragraph::new(text)
         .style(match app.input_mode {
-            InputMode::View | InputMode::Export => Style::default(),
+            InputMode::View
+            | InputMode::Export
+            | InputMode::Import
+            | InputMode::MoveTask
+            | InputMode::ConfirmCompleteSubtasks => Style::default(),
+            InputMode::NewProject => Style::default().fg(Color::Green),
             InputMode::Adding => Style::default().fg(Color::Green),
             InputMode::EditingExisting => Style::default().fg(Color::Yellow),
         })
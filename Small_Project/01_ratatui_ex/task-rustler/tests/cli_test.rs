@@ -0,0 +1,270 @@
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use std::path::PathBuf;
+    use task_rustler::cli::{default_db_path, run, Cli};
+    use task_rustler::date::INVALID_DUE;
+    use task_rustler::task::Priority;
+    use task_rustler::task_manager::TasksService;
+
+    fn taskrs(tasks: &TasksService, args: &[&str]) -> anyhow::Result<String> {
+        let cli = Cli::try_parse_from([&["taskrs"], args].concat())?;
+        let mut out = Vec::new();
+        run(cli.command.unwrap(), tasks, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn add_prints_the_new_id() {
+        let tasks = TasksService::default();
+        let out = taskrs(
+            &tasks,
+            &[
+                "add",
+                "Write report",
+                "--desc",
+                "Q3",
+                "--due",
+                "12-10-2026",
+                "--priority",
+                "high",
+            ],
+        )
+        .unwrap();
+        assert_eq!(out, "1\n");
+        let task = tasks.get_task(1).unwrap().unwrap();
+        assert_eq!(task.title, "Write report");
+        assert_eq!(task.description, "Q3");
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(String::try_from(task.date).unwrap(), "12-10-2026");
+    }
+
+    #[test]
+    fn add_rejects_bad_dates_and_priorities() {
+        let tasks = TasksService::default();
+        let err = taskrs(&tasks, &["add", "title", "--due", "2026/10/12"]).unwrap_err();
+        assert_eq!(err.to_string(), INVALID_DUE);
+        assert!(format!("{err:#}").ends_with("Unknown date 2026/10/12"));
+        assert!(taskrs(&tasks, &["add", "title", "--priority", "urgent"]).is_err());
+        assert!(tasks.is_empty().unwrap());
+    }
+
+    #[test]
+    fn list_filters_and_prints_json() {
+        let tasks = TasksService::default();
+        taskrs(&tasks, &["add", "first", "--due", "01-01-2000"]).unwrap();
+        taskrs(&tasks, &["add", "second", "--priority", "high"]).unwrap();
+        taskrs(&tasks, &["done", "2"]).unwrap();
+
+        let out = taskrs(&tasks, &["list", "--json"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json[0]["title"], "second");
+        assert_eq!(json[0]["completed"], true);
+        assert_eq!(json[0]["due"], serde_json::Value::Null);
+        assert_eq!(json[1]["priority"], "low");
+        assert_eq!(json[1]["due"], "2000-01-01");
+
+        let out = taskrs(&tasks, &["list", "--filter", "pending"]).unwrap();
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.contains("first"));
+        let out = taskrs(&tasks, &["list", "--filter", "overdue"]).unwrap();
+        assert!(out.contains("first"));
+        let out = taskrs(&tasks, &["list", "--filter", "done"]).unwrap();
+        assert!(out.contains("[x]") && out.contains("second"));
+    }
+
+    #[test]
+    fn tasks_due_at_a_time() {
+        let tasks = TasksService::default();
+        taskrs(&tasks, &["add", "past", "--due", "01-01-2000 23:59 UTC"]).unwrap();
+        taskrs(&tasks, &["add", "future", "--due", "01-01-2100 08:00"]).unwrap();
+        assert!(taskrs(&tasks, &["add", "bad", "--due", "01-01-2000 8am"]).is_err());
+
+        let out = taskrs(&tasks, &["list", "--filter", "overdue"]).unwrap();
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.contains("01-01-2000 23:59 UTC past"));
+        let out = taskrs(&tasks, &["list", "--json"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json[0]["due_time"], "23:59");
+        assert_eq!(json[0]["timezone"], "UTC");
+        assert_eq!(json[1]["due_time"], "08:00");
+        assert_eq!(json[1]["timezone"], serde_json::Value::Null);
+
+        taskrs(&tasks, &["edit", "2", "--no-due"]).unwrap();
+        let task = tasks.get_task(2).unwrap().unwrap();
+        assert!(task.date.0.is_none() && task.due_time.is_none());
+    }
+
+    #[test]
+    fn tags_from_the_command_line() {
+        let tasks = TasksService::default();
+        taskrs(&tasks, &["add", "first", "--tag", "work", "--tag", "q3"]).unwrap();
+        taskrs(&tasks, &["add", "second"]).unwrap();
+        let out = taskrs(&tasks, &["list", "--tag", "work"]).unwrap();
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.contains("first #q3 #work"));
+        let out = taskrs(&tasks, &["list", "--json"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json[0]["tags"], serde_json::json!(["q3", "work"]));
+        assert_eq!(json[1]["tags"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn edit_and_rm() {
+        let tasks = TasksService::default();
+        taskrs(&tasks, &["add", "title", "--due", "01-02-2026"]).unwrap();
+        taskrs(
+            &tasks,
+            &[
+                "edit",
+                "1",
+                "--title",
+                "new title",
+                "--no-due",
+                "--priority",
+                "medium",
+            ],
+        )
+        .unwrap();
+        let task = tasks.get_task(1).unwrap().unwrap();
+        assert_eq!(task.title, "new title");
+        assert!(task.date.0.is_none());
+        assert_eq!(task.priority, Priority::Medium);
+
+        assert!(taskrs(&tasks, &["edit", "7", "--title", "x"]).is_err());
+        taskrs(&tasks, &["rm", "1"]).unwrap();
+        assert!(tasks.is_empty().unwrap());
+        assert_eq!(
+            taskrs(&tasks, &["rm", "1"]).unwrap_err().to_string(),
+            "No task with id 1"
+        );
+        assert!(taskrs(&tasks, &["done", "1"]).is_err());
+    }
+
+    #[test]
+    fn export_to_file() {
+        let tasks = TasksService::default();
+        taskrs(&tasks, &["add", "title"]).unwrap();
+        let path = std::env::temp_dir().join(format!("taskrs-cli-{}.ics", std::process::id()));
+        let out = taskrs(
+            &tasks,
+            &["export", "--format", "ics", "-o", path.to_str().unwrap()],
+        )
+        .unwrap();
+        assert!(out.is_empty());
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("BEGIN:VCALENDAR"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn import_from_file() {
+        let tasks = TasksService::default();
+        taskrs(&tasks, &["add", "title", "--due", "01-02-2026 10:00 UTC"]).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("taskrs-cli-import-{}.ics", std::process::id()));
+        let path = path.to_str().unwrap();
+        taskrs(&tasks, &["export", "-o", path]).unwrap();
+
+        let other = TasksService::default();
+        let out = taskrs(&other, &["import", path, "--project", "Work"]).unwrap();
+        assert_eq!(out, "1\n");
+        assert_eq!(taskrs(&other, &["import", path]).unwrap(), "0\n");
+        let out = taskrs(&other, &["list", "--project", "Work"]).unwrap();
+        assert!(out.contains("01-02-2026 10:00 UTC title"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn projects_from_the_command_line() {
+        let tasks = TasksService::default();
+        taskrs(&tasks, &["add", "inbox task"]).unwrap();
+        taskrs(&tasks, &["add", "report", "--project", "Work"]).unwrap();
+        taskrs(&tasks, &["add", "meeting", "--project", "work"]).unwrap();
+        assert_eq!(
+            taskrs(&tasks, &["projects"]).unwrap(),
+            "   1 Inbox\n   2 Work\n"
+        );
+
+        let out = taskrs(&tasks, &["list", "--project", "Work"]).unwrap();
+        assert_eq!(out.lines().count(), 2);
+        assert!(taskrs(&tasks, &["list", "--project", "Home"]).is_err());
+
+        taskrs(&tasks, &["mv", "1", "Home"]).unwrap();
+        let out = taskrs(&tasks, &["list", "--project", "home"]).unwrap();
+        assert!(out.contains("inbox task"));
+        assert!(taskrs(&tasks, &["mv", "42", "Home"]).is_err());
+
+        let ics = taskrs(&tasks, &["export", "--project", "work"]).unwrap();
+        assert!(ics.contains("NAME:Work"));
+        assert_eq!(ics.matches("BEGIN:VTODO").count(), 2);
+    }
+
+    #[test]
+    fn add_subtask_from_the_command_line() {
+        let tasks = TasksService::default();
+        taskrs(&tasks, &["add", "report", "--project", "Work"]).unwrap();
+        assert_eq!(
+            taskrs(&tasks, &["add", "draft", "--parent", "1"]).unwrap(),
+            "2\n"
+        );
+        let task = tasks.get_task(2).unwrap().unwrap();
+        assert_eq!(task.parent_id, Some(1));
+        assert_eq!(task.project_id, tasks.project_by_name("Work").unwrap().id);
+        assert!(taskrs(&tasks, &["add", "draft", "--parent", "42"]).is_err());
+        assert!(taskrs(&tasks, &["add", "x", "--parent", "1", "--project", "Home"]).is_err());
+    }
+
+    #[test]
+    fn recurring_tasks_from_the_command_line() {
+        let tasks = TasksService::default();
+        taskrs(
+            &tasks,
+            &[
+                "add",
+                "report",
+                "--due",
+                "14-10-2024",
+                "--repeat",
+                "weekly mon",
+            ],
+        )
+        .unwrap();
+        assert!(taskrs(&tasks, &["add", "x", "--repeat", "sometimes"]).is_err());
+        assert_eq!(taskrs(&tasks, &["done", "1"]).unwrap(), "2\n");
+        let out = taskrs(&tasks, &["list", "--filter", "pending"]).unwrap();
+        assert!(out.contains("21-10-2024 report (weekly mon)"), "{out}");
+
+        taskrs(&tasks, &["edit", "2", "--no-repeat"]).unwrap();
+        assert_eq!(tasks.get_task(2).unwrap().unwrap().recurrence, None);
+        assert_eq!(taskrs(&tasks, &["done", "2"]).unwrap(), "");
+    }
+
+    #[test]
+    fn default_db_path_follows_xdg() {
+        let path = default_db_path(Some("/data".into()), Some("/home/me".into())).unwrap();
+        assert_eq!(path, PathBuf::from("/data/task-rustler/tasks.db"));
+        // Relative values of XDG_DATA_HOME are invalid and ignored.
+        let path = default_db_path(Some("data".into()), Some("/home/me".into())).unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from("/home/me/.local/share/task-rustler/tasks.db")
+        );
+        assert!(default_db_path(None, None).is_err());
+    }
+
+    #[test]
+    fn db_flags() {
+        let cli = Cli::try_parse_from(["taskrs", "list", "--memory"]).unwrap();
+        assert_eq!(cli.db_path().unwrap(), "");
+        assert!(Cli::try_parse_from(["taskrs", "--memory", "--db", "x.db"]).is_err());
+
+        let dir = std::env::temp_dir().join(format!("taskrs-db-{}", std::process::id()));
+        let db = dir.join("nested").join("tasks.db");
+        let cli = Cli::try_parse_from(["taskrs", "--db", db.to_str().unwrap()]).unwrap();
+        assert_eq!(cli.db_path().unwrap(), db.to_str().unwrap());
+        assert!(db.parent().unwrap().is_dir());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,27 +1,212 @@
 #[cfg(test)]
 mod test {
-    use task_rustler::app::AppContext;
-    use task_rustler::command::{AddTaskCommand, Command};
+    use task_rustler::app::{AppContext, InputMode};
+    use task_rustler::command::{
+        AddTaskCommand, CancelCompleteTaskCommand, CollapseTaskCommand, Command,
+        CompleteTaskCommand, EnterAddSubtaskModeCommand, EnterImportModeCommand,
+        EnterMoveTaskModeCommand, ExpandTaskCommand, FinishEditingExistingTaskCommand,
+        FinishMoveTaskCommand, FinishingExportCommand, FinishingImportCommand, NextProjectCommand,
+        StartEditingExistingTaskCommand, ToggleTaskStatusCommand,
+    };
+    use task_rustler::date::INVALID_DUE;
+    use task_rustler::project::INBOX_ID;
+    use task_rustler::recurrence::Recurrence;
+    use task_rustler::task::Task;
 
     #[test]
     fn add_task_command_test_wrong_date_format() {
-        let mut app = AppContext::new(String::new());
+        let mut app = AppContext::new(String::new()).unwrap();
         app.input_title = String::from("test title");
         app.input_description = String::from("test description");
         app.input_date = String::from("invalid date");
         let res = AddTaskCommand.execute(&mut app);
         assert!(res.is_err());
-        assert_eq!(res.err().unwrap().to_string(), "Invalid date format, use dd-mm-yyyy")
+        assert_eq!(res.err().unwrap().to_string(), INVALID_DUE)
     }
 
     #[test]
     fn add_task_command_test_empty_title() {
-        let mut app = AppContext::new(String::new());
+        let mut app = AppContext::new(String::new()).unwrap();
         app.input_title = String::from("");
         app.input_description = String::from("test description");
         app.input_date = String::from("10-12-2012");
         let res = AddTaskCommand.execute(&mut app);
         assert!(res.is_err());
-        assert_eq!(res.err().unwrap().to_string(), "You must insert at least a title for the task");
+        assert_eq!(
+            res.err().unwrap().to_string(),
+            "You must insert at least a title for the task"
+        );
+    }
+
+    #[test]
+    fn tasks_are_added_to_the_viewed_project_and_moved() {
+        let mut app = AppContext::new(String::new()).unwrap();
+        let work = app.tasks_service.add_project("Work").unwrap();
+        app.refresh_task_list().unwrap();
+        NextProjectCommand.execute(&mut app).unwrap();
+        assert_eq!(app.project_filter, Some(INBOX_ID));
+        NextProjectCommand.execute(&mut app).unwrap();
+        assert_eq!(app.current_project().unwrap().name, "Work");
+
+        app.input_title = String::from("report");
+        AddTaskCommand.execute(&mut app).unwrap();
+        assert_eq!(app.task_list.items.len(), 1);
+        assert_eq!(app.task_list.items[0].project_id, work);
+
+        app.task_list.state.select(Some(0));
+        EnterMoveTaskModeCommand.execute(&mut app).unwrap();
+        assert_eq!(app.move_target, 1);
+        app.previous_move_target();
+        FinishMoveTaskCommand.execute(&mut app).unwrap();
+        assert!(app.task_list.items.is_empty());
+        assert_eq!(app.projects[0].1, 1);
+
+        // Past the last project every task is listed again
+        NextProjectCommand.execute(&mut app).unwrap();
+        assert_eq!(app.project_filter, None);
+        assert_eq!(app.task_list.items.len(), 1);
+    }
+
+    #[test]
+    fn subtasks_are_listed_under_their_parent() {
+        let mut app = AppContext::new(String::new()).unwrap();
+        let parent = app.tasks_service.add_new_task(&Task::default()).unwrap();
+        let other = app.tasks_service.add_new_task(&Task::default()).unwrap();
+        app.refresh_task_list().unwrap();
+        app.task_list.state.select(Some(0));
+        EnterAddSubtaskModeCommand.execute(&mut app).unwrap();
+        app.input_title = String::from("step");
+        AddTaskCommand.execute(&mut app).unwrap();
+        assert_eq!(app.new_task_parent, None);
+
+        let ids: Vec<i32> = app.task_list.items.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![parent, 3, other]);
+        assert_eq!(app.depths[&3], 1);
+        assert_eq!(app.subtask_counts[&parent], (0, 1));
+
+        CollapseTaskCommand.execute(&mut app).unwrap();
+        assert_eq!(app.task_list.items.len(), 2);
+        ExpandTaskCommand.execute(&mut app).unwrap();
+        assert_eq!(app.task_list.items.len(), 3);
+    }
+
+    #[test]
+    fn completing_a_parent_asks_about_its_subtasks() {
+        let mut app = AppContext::new(String::new()).unwrap();
+        let parent = app.tasks_service.add_new_task(&Task::default()).unwrap();
+        app.tasks_service
+            .add_subtask(parent, &Task::default())
+            .unwrap();
+        app.refresh_task_list().unwrap();
+        app.task_list.state.select(Some(0));
+
+        ToggleTaskStatusCommand.execute(&mut app).unwrap();
+        assert!(matches!(app.input_mode, InputMode::ConfirmCompleteSubtasks));
+        assert!(!app.task_list.items[0].completed);
+        CancelCompleteTaskCommand.execute(&mut app).unwrap();
+        assert!(matches!(app.input_mode, InputMode::View));
+
+        ToggleTaskStatusCommand.execute(&mut app).unwrap();
+        CompleteTaskCommand {
+            with_subtasks: false,
+        }
+        .execute(&mut app)
+        .unwrap();
+        assert!(app.task_list.items[0].completed);
+        assert_eq!(app.subtask_counts[&parent], (0, 1));
+
+        ToggleTaskStatusCommand.execute(&mut app).unwrap();
+        ToggleTaskStatusCommand.execute(&mut app).unwrap();
+        CompleteTaskCommand {
+            with_subtasks: true,
+        }
+        .execute(&mut app)
+        .unwrap();
+        assert!(app.task_list.items.iter().all(|t| t.completed));
+        assert_eq!(app.subtask_counts[&parent], (1, 1));
+    }
+
+    #[test]
+    fn completing_a_recurring_task_schedules_the_next_one() {
+        let mut app = AppContext::new(String::new()).unwrap();
+        app.input_title = String::from("invoice");
+        app.input_date = String::from("01-10-2024");
+        app.input_recurrence = String::from("monthly 1");
+        AddTaskCommand.execute(&mut app).unwrap();
+        assert_eq!(
+            app.task_list.items[0].recurrence,
+            Some(Recurrence::Monthly(1))
+        );
+
+        app.task_list.state.select(Some(0));
+        ToggleTaskStatusCommand.execute(&mut app).unwrap();
+        assert_eq!(app.task_list.items.len(), 2);
+        let next = app.task_list.items.iter().find(|t| !t.completed).unwrap();
+        assert_eq!(String::try_from(next.date.clone()).unwrap(), "01-11-2024");
+
+        app.input_title = String::from("invalid");
+        app.input_date = String::from("01-12-2024");
+        app.input_recurrence = String::from("sometimes");
+        assert!(AddTaskCommand.execute(&mut app).is_err());
+        // What was typed is kept to be fixed
+        assert_eq!(app.input_title, "invalid");
+        assert_eq!(app.input_date, "01-12-2024");
+        assert_eq!(app.input_recurrence, "sometimes");
+        assert_eq!(app.task_list.items.len(), 2);
+    }
+
+    #[test]
+    fn a_failed_edit_leaves_the_task_unchanged() {
+        let mut app = AppContext::new(String::new()).unwrap();
+        app.input_title = String::from("invoice");
+        app.input_date = String::from("01-10-2024");
+        AddTaskCommand.execute(&mut app).unwrap();
+        app.task_list.state.select(Some(0));
+        StartEditingExistingTaskCommand.execute(&mut app).unwrap();
+        app.input_recurrence = String::from("monthly 1");
+        app.input_date = String::from("not a date");
+        assert!(FinishEditingExistingTaskCommand.execute(&mut app).is_err());
+        assert_eq!(app.task_list.items[0].recurrence, None);
+        assert_eq!(app.input_recurrence, "monthly 1");
+
+        app.input_date = String::from("02-10-2024");
+        FinishEditingExistingTaskCommand.execute(&mut app).unwrap();
+        assert_eq!(
+            app.task_list.items[0].recurrence,
+            Some(Recurrence::Monthly(1))
+        );
+        assert!(app.input_date.is_empty());
+    }
+
+    #[test]
+    fn tasks_are_imported_into_the_viewed_project() {
+        let mut app = AppContext::new(String::new()).unwrap();
+        app.input_title = String::from("exported");
+        AddTaskCommand.execute(&mut app).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("taskrs-tui-import-{}.ics", std::process::id()));
+        app.input_export_path = path.to_str().unwrap().to_string();
+        FinishingExportCommand.execute(&mut app).unwrap();
+
+        let mut app = AppContext::new(String::new()).unwrap();
+        let work = app.tasks_service.add_project("Work").unwrap();
+        app.refresh_task_list().unwrap();
+        NextProjectCommand.execute(&mut app).unwrap();
+        NextProjectCommand.execute(&mut app).unwrap();
+        EnterImportModeCommand.execute(&mut app).unwrap();
+        assert!(matches!(app.input_mode, InputMode::Import));
+        app.input_import_path = path.to_str().unwrap().to_string();
+        FinishingImportCommand.execute(&mut app).unwrap();
+        assert_eq!(app.task_list.items.len(), 1);
+        assert_eq!(app.task_list.items[0].title, "exported");
+        assert_eq!(app.task_list.items[0].project_id, work);
+
+        app.input_import_path = path.to_str().unwrap().to_string();
+        FinishingImportCommand.execute(&mut app).unwrap();
+        assert_eq!(app.task_list.items.len(), 1);
+        std::fs::remove_file(path).unwrap();
+
+        app.input_import_path = String::from("/nonexistent/tasks.ics");
+        assert!(FinishingImportCommand.execute(&mut app).is_err());
     }
 }
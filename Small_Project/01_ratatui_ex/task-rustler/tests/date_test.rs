@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+    use chrono_tz::Tz;
+    use task_rustler::date::{format_due, parse_due, resolve_date, DueTime, TaskDate};
+    use task_rustler::task::Task;
+
+    // A Wednesday
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()
+    }
+
+    fn day(d: u32, m: u32, y: i32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn due_date_with_time_and_timezone() {
+        let date = TaskDate(NaiveDate::from_ymd_opt(2026, 10, 12));
+        assert_eq!(
+            parse_due("12-10-2026", today()).unwrap(),
+            (date.clone(), None)
+        );
+        let (parsed, due_time) = parse_due("12-10-2026 09:30", today()).unwrap();
+        assert_eq!(parsed, date);
+        assert_eq!(
+            due_time,
+            Some(DueTime {
+                time: at(9, 30),
+                timezone: None
+            })
+        );
+        let (_, due_time) = parse_due("12-10-2026 09:30 Europe/Rome", today()).unwrap();
+        assert_eq!(due_time.unwrap().timezone, Some(Tz::Europe__Rome));
+        assert_eq!(
+            format_due(&date, due_time.as_ref()),
+            "12-10-2026 09:30 Europe/Rome"
+        );
+
+        assert!(parse_due("12-10-2026 25:00", today()).is_err());
+        assert_eq!(
+            parse_due("12-10-2026 09:30 Mars/Olympus", today())
+                .unwrap_err()
+                .to_string(),
+            "Unknown timezone Mars/Olympus"
+        );
+        assert!(parse_due("12-10-2026 09:30 UTC later", today()).is_err());
+    }
+
+    #[test]
+    fn relative_dates() {
+        let resolve = |input| resolve_date(input, today()).unwrap();
+        assert_eq!(resolve("today"), today());
+        assert_eq!(resolve("Tomorrow"), day(15, 10, 2026));
+        assert_eq!(resolve("fri"), day(16, 10, 2026));
+        assert_eq!(resolve("friday"), day(16, 10, 2026));
+        // The same weekday is a week later, never today
+        assert_eq!(resolve("wed"), day(21, 10, 2026));
+        assert_eq!(resolve("mon"), day(19, 10, 2026));
+        assert_eq!(resolve("next monday"), day(19, 10, 2026));
+        assert_eq!(resolve("next fri"), day(23, 10, 2026));
+        assert_eq!(resolve("+3d"), day(17, 10, 2026));
+        assert_eq!(resolve("+2w"), day(28, 10, 2026));
+        assert_eq!(resolve("eom"), day(31, 10, 2026));
+        assert_eq!(
+            resolve_date("eom", day(10, 2, 2028)).unwrap(),
+            day(29, 2, 2028)
+        );
+        assert_eq!(resolve("2026-12-25"), day(25, 12, 2026));
+        assert_eq!(resolve("25-12-2026"), day(25, 12, 2026));
+
+        for input in ["", "someday", "next", "+d", "+3m", "-3d", "31-02-2026"] {
+            assert!(resolve_date(input, today()).is_err(), "{input}");
+        }
+        assert_eq!(
+            resolve_date("someday", today()).unwrap_err().to_string(),
+            "Unknown date someday"
+        );
+    }
+
+    #[test]
+    fn relative_dates_with_a_time() {
+        let (date, due_time) = parse_due("next mon 09:30 UTC", today()).unwrap();
+        assert_eq!(date, TaskDate(Some(day(19, 10, 2026))));
+        assert_eq!(
+            due_time,
+            Some(DueTime {
+                time: at(9, 30),
+                timezone: Some(Tz::UTC)
+            })
+        );
+        let (date, _) = parse_due("+1d 18:00", today()).unwrap();
+        assert_eq!(date, TaskDate(Some(day(15, 10, 2026))));
+        assert!(parse_due("09:30", today()).is_err());
+    }
+
+    #[test]
+    fn overdue_to_the_minute() {
+        let task = Task {
+            date: TaskDate(NaiveDate::from_ymd_opt(2026, 10, 12)),
+            due_time: Some(DueTime {
+                time: at(9, 30),
+                timezone: Some(Tz::Europe__Rome),
+            }),
+            ..Task::default()
+        };
+        // 09:30 in Rome is 07:30 UTC during summer time
+        assert_eq!(
+            task.due_time.unwrap().on(task.date.0.unwrap()),
+            Utc.with_ymd_and_hms(2026, 10, 12, 7, 30, 0).unwrap()
+        );
+        assert!(!task.is_overdue(Utc.with_ymd_and_hms(2026, 10, 12, 7, 30, 0).unwrap()));
+        assert!(task.is_overdue(Utc.with_ymd_and_hms(2026, 10, 12, 7, 31, 0).unwrap()));
+
+        let completed = Task {
+            completed: true,
+            ..task
+        };
+        assert!(!completed.is_overdue(Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap()));
+        assert!(!Task::default().is_overdue(Utc::now()));
+    }
+
+    #[test]
+    fn times_skipped_by_daylight_saving_are_moved_an_hour_later() {
+        let due_time = DueTime {
+            time: at(2, 30),
+            timezone: Some(Tz::Europe__Rome),
+        };
+        // Clocks went from 02:00 to 03:00 that night, 03:30 summer time is 01:30 UTC
+        assert_eq!(
+            due_time.on(NaiveDate::from_ymd_opt(2026, 3, 29).unwrap()),
+            Utc.with_ymd_and_hms(2026, 3, 29, 1, 30, 0).unwrap()
+        );
+    }
+}
@@ -1,40 +1,62 @@
 #[cfg(test)]
 mod test {
-    use chrono::NaiveDate;
-    use task_rustler::date::{TaskDate, DATE_FORMAT};
+    use chrono::{NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+    use chrono_tz::Tz;
+    use icalendar::{CalendarDateTime, Component, DatePerhapsTime, TodoStatus};
+    use task_rustler::date::{DueTime, TaskDate, DATE_FORMAT};
+    use task_rustler::export::{export_tasks_to_icalendar, task_uid};
+    use task_rustler::project::INBOX_ID;
+    use task_rustler::recurrence::Recurrence;
     use task_rustler::task::{Priority, Task};
-    use task_rustler::export::export_tasks_to_icalendar;
-    use icalendar::{Component, DatePerhapsTime, TodoStatus};
+
+    const DATABASE_ID: &str = "5c9a3e0b-7d21-4f6a-9b18-0e2d4c6f8a13";
 
     #[test]
-    fn tasks_to_icalendar(){
-        let task1 = Task{
+    fn tasks_to_icalendar() {
+        let task1 = Task {
             id: 0,
             title: "First task".to_string(),
             description: "Task n 1".to_string(),
             completed: false,
             priority: Priority::Low,
-            date: TaskDate(Some(NaiveDate::parse_from_str("15-10-2024", DATE_FORMAT).unwrap()))
+            date: TaskDate(Some(
+                NaiveDate::parse_from_str("15-10-2024", DATE_FORMAT).unwrap(),
+            )),
+            tags: vec!["home".to_string(), "work".to_string()],
+            project_id: INBOX_ID,
+            parent_id: None,
+            recurrence: None,
+            due_time: None,
+            uid: None,
         };
 
-        let task2 = Task{
+        let task2 = Task {
             id: 1,
             title: "Second task".to_string(),
             description: "Task n 2".to_string(),
             completed: true,
             priority: Priority::High,
-            date: TaskDate(None)
+            date: TaskDate(None),
+            tags: vec![],
+            project_id: INBOX_ID,
+            parent_id: None,
+            recurrence: None,
+            due_time: None,
+            uid: None,
         };
 
         let tasks = vec![task1, task2];
-        let calendar = export_tasks_to_icalendar("Task Rustler", &tasks );
+        let calendar = export_tasks_to_icalendar("Task Rustler", DATABASE_ID, &tasks);
         assert_eq!(calendar.get_name().unwrap(), "Task Rustler");
 
         let todo1 = calendar.components[0].as_todo().unwrap();
         assert_eq!(todo1.get_summary().unwrap(), "First task");
         assert_eq!(todo1.get_description().unwrap(), "Task n 1");
-        assert_eq!(todo1.get_priority().unwrap(), 3);
-        assert_eq!(todo1.get_end().unwrap(), DatePerhapsTime::Date(NaiveDate::parse_from_str("15-10-2024", DATE_FORMAT).unwrap()));
+        assert_eq!(todo1.get_priority().unwrap(), 9);
+        assert_eq!(
+            todo1.get_end().unwrap(),
+            DatePerhapsTime::Date(NaiveDate::parse_from_str("15-10-2024", DATE_FORMAT).unwrap())
+        );
         assert_eq!(todo1.get_status().unwrap(), TodoStatus::NeedsAction);
 
         let todo2 = calendar.components[1].as_todo().unwrap();
@@ -44,4 +66,137 @@ mod test {
         assert_eq!(todo2.get_end(), None);
         assert_eq!(todo2.get_status().unwrap(), TodoStatus::Completed);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn tags_are_exported_as_categories() {
+        let task = Task {
+            tags: vec!["home".to_string(), "urgent".to_string()],
+            ..Task::default()
+        };
+        let ics = export_tasks_to_icalendar("Task Rustler", DATABASE_ID, &[task]).to_string();
+        assert!(ics.contains("CATEGORIES:home\r\n"));
+        assert!(ics.contains("CATEGORIES:urgent\r\n"));
+        let ics =
+            export_tasks_to_icalendar("Task Rustler", DATABASE_ID, &[Task::default()]).to_string();
+        assert!(!ics.contains("CATEGORIES"));
+    }
+
+    #[test]
+    fn subtasks_are_related_to_their_parent() {
+        let parent = Task {
+            id: 1,
+            ..Task::default()
+        };
+        let subtask = Task {
+            id: 2,
+            parent_id: Some(1),
+            ..Task::default()
+        };
+        let calendar = export_tasks_to_icalendar("Task Rustler", DATABASE_ID, &[parent, subtask]);
+        let todos: Vec<_> = calendar
+            .components
+            .iter()
+            .filter_map(|c| c.as_todo())
+            .collect();
+        assert_eq!(todos[0].get_uid(), Some(task_uid(DATABASE_ID, 1).as_str()));
+        assert_eq!(todos[0].property_value("RELATED-TO"), None);
+        assert_eq!(
+            todos[1].property_value("RELATED-TO"),
+            Some(task_uid(DATABASE_ID, 1).as_str())
+        );
+    }
+
+    #[test]
+    fn stored_uids_are_exported() {
+        let parent = Task {
+            id: 1,
+            uid: Some("call-bob@example.com".to_string()),
+            ..Task::default()
+        };
+        let subtask = Task {
+            id: 2,
+            parent_id: Some(1),
+            uid: Some(task_uid(DATABASE_ID, 2)),
+            ..Task::default()
+        };
+        let orphan = Task {
+            id: 3,
+            parent_id: Some(4),
+            ..Task::default()
+        };
+        let calendar =
+            export_tasks_to_icalendar("Task Rustler", DATABASE_ID, &[parent, subtask, orphan]);
+        let todos: Vec<_> = calendar
+            .components
+            .iter()
+            .filter_map(|c| c.as_todo())
+            .collect();
+        assert_eq!(todos[0].get_uid(), Some("call-bob@example.com"));
+        assert_eq!(todos[1].get_uid(), Some(task_uid(DATABASE_ID, 2).as_str()));
+        assert_eq!(
+            todos[1].property_value("RELATED-TO"),
+            Some("call-bob@example.com")
+        );
+        // The parent isn't in the calendar, it was given its UID from its id
+        assert_eq!(
+            todos[2].property_value("RELATED-TO"),
+            Some(task_uid(DATABASE_ID, 4).as_str())
+        );
+    }
+
+    #[test]
+    fn recurring_tasks_have_a_rrule() {
+        let task = Task {
+            recurrence: Some(Recurrence::Weekly(vec![Weekday::Mon])),
+            ..Task::default()
+        };
+        let calendar =
+            export_tasks_to_icalendar("Task Rustler", DATABASE_ID, &[task, Task::default()]);
+        let todos: Vec<_> = calendar
+            .components
+            .iter()
+            .filter_map(|c| c.as_todo())
+            .collect();
+        assert_eq!(
+            todos[0].property_value("RRULE"),
+            Some("FREQ=WEEKLY;BYDAY=MO")
+        );
+        assert_eq!(todos[1].property_value("RRULE"), None);
+    }
+
+    #[test]
+    fn timed_tasks_have_a_due_time() {
+        let due = |timezone| Task {
+            date: TaskDate(NaiveDate::from_ymd_opt(2026, 10, 12)),
+            due_time: Some(DueTime {
+                time: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+                timezone,
+            }),
+            ..Task::default()
+        };
+        let tasks = [due(Some(Tz::UTC)), due(Some(Tz::Europe__Rome))];
+        let calendar = export_tasks_to_icalendar("Task Rustler", DATABASE_ID, &tasks);
+        let todos: Vec<_> = calendar
+            .components
+            .iter()
+            .filter_map(|c| c.as_todo())
+            .collect();
+        assert_eq!(
+            todos[0].get_due(),
+            Some(DatePerhapsTime::DateTime(CalendarDateTime::Utc(
+                Utc.with_ymd_and_hms(2026, 10, 12, 9, 30, 0).unwrap()
+            )))
+        );
+        assert_eq!(
+            todos[1].get_due(),
+            Some(DatePerhapsTime::DateTime(CalendarDateTime::WithTimezone {
+                date_time: NaiveDate::from_ymd_opt(2026, 10, 12)
+                    .unwrap()
+                    .and_hms_opt(9, 30, 0)
+                    .unwrap(),
+                tzid: "Europe/Rome".to_string(),
+            }))
+        );
+        assert_eq!(todos[1].get_start(), None);
+    }
+}
@@ -0,0 +1,12 @@
+-- Database as created by task rustler 0.4.0, before schema migrations.
+CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL,
+            completed BOOLEAN NOT NULL,
+            priority INTEGER NOT NULL,
+            end_date DATE
+        );
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Pay rent', 'Every month', 0, 1, '01-11-2024');
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Call Bob', '', 1, 2, NULL);
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Renew passport', 'Bring photos', 0, 3, '15-03-2025');
@@ -0,0 +1,13 @@
+-- Version 2: dates stored as yyyy-mm-dd.
+CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL,
+            completed BOOLEAN NOT NULL,
+            priority INTEGER NOT NULL,
+            end_date DATE
+        );
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Pay rent', 'Every month', 0, 1, '2024-11-01');
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Call Bob', '', 1, 2, NULL);
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Renew passport', 'Bring photos', 0, 3, '2025-03-15');
+PRAGMA user_version = 2;
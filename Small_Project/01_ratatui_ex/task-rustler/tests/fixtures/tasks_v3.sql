@@ -0,0 +1,24 @@
+-- Version 3: tags.
+CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL,
+            completed BOOLEAN NOT NULL,
+            priority INTEGER NOT NULL,
+            end_date DATE
+        );
+CREATE TABLE tags (
+                    id INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+                );
+CREATE TABLE task_tags (
+                    task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                    PRIMARY KEY (task_id, tag_id)
+                );
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Pay rent', 'Every month', 0, 1, '2024-11-01');
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Call Bob', '', 1, 2, NULL);
+INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('Renew passport', 'Bring photos', 0, 3, '2025-03-15');
+INSERT INTO tags (name) VALUES ('home');
+INSERT INTO task_tags (task_id, tag_id) VALUES (1, 1);
+PRAGMA user_version = 3;
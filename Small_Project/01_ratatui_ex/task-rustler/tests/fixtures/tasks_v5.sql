@@ -0,0 +1,31 @@
+-- Version 5: subtasks.
+CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL,
+            completed BOOLEAN NOT NULL,
+            priority INTEGER NOT NULL,
+            end_date DATE
+        );
+CREATE TABLE tags (
+                    id INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+                );
+CREATE TABLE task_tags (
+                    task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+                    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                    PRIMARY KEY (task_id, tag_id)
+                );
+CREATE TABLE projects (
+                    id INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+                );
+INSERT INTO projects (id, name) VALUES (1, 'Inbox');
+ALTER TABLE tasks ADD COLUMN project_id INTEGER REFERENCES projects(id);
+INSERT INTO tasks (title, description, completed, priority, end_date, project_id) VALUES ('Pay rent', 'Every month', 0, 1, '2024-11-01', 1);
+INSERT INTO tasks (title, description, completed, priority, end_date, project_id) VALUES ('Call Bob', '', 1, 2, NULL, 1);
+INSERT INTO tasks (title, description, completed, priority, end_date, project_id) VALUES ('Renew passport', 'Bring photos', 0, 3, '2025-03-15', 1);
+INSERT INTO tags (name) VALUES ('home');
+INSERT INTO task_tags (task_id, tag_id) VALUES (1, 1);
+ALTER TABLE tasks ADD COLUMN parent_id INTEGER REFERENCES tasks(id) ON DELETE CASCADE;
+PRAGMA user_version = 5;
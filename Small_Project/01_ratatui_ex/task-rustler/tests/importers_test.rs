@@ -0,0 +1,234 @@
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, NaiveTime};
+    use chrono_tz::Tz;
+    use std::fs;
+    use std::path::PathBuf;
+    use task_rustler::date::{DueTime, TaskDate};
+    use task_rustler::export::{export_tasks_to_icalendar, import_tasks_from_icalendar, task_uid};
+    use task_rustler::task::{Priority, Task};
+    use task_rustler::task_manager::TasksService;
+
+    const DATABASE_ID: &str = "5c9a3e0b-7d21-4f6a-9b18-0e2d4c6f8a13";
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//Other app//EN\r
+BEGIN:VTODO\r
+UID:call-bob@example.com\r
+SUMMARY:Call Bob\r
+DESCRIPTION:About the\r
+  offer\r
+PRIORITY:1\r
+STATUS:COMPLETED\r
+DUE;TZID=Europe/Rome:20261012T093045\r
+END:VTODO\r
+BEGIN:VEVENT\r
+UID:meeting@example.com\r
+SUMMARY:Meeting\r
+END:VEVENT\r
+BEGIN:VTODO\r
+UID:renew@example.com\r
+SUMMARY:Renew passport\r
+PRIORITY:5\r
+DTSTART;VALUE=DATE:20261101\r
+END:VTODO\r
+BEGIN:VTODO\r
+SUMMARY:Pay rent\r
+PRIORITY:9\r
+STATUS:NEEDS-ACTION\r
+DUE:20261101T170000Z\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("taskrs-import-{}-{name}", std::process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn todos_are_read_as_tasks() {
+        let path = temp_file("other.ics", CALENDAR);
+        let imported = import_tasks_from_icalendar(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(imported.len(), 3);
+
+        let (uid, call) = &imported[0];
+        assert_eq!(uid.as_deref(), Some("call-bob@example.com"));
+        assert_eq!(call.title, "Call Bob");
+        assert_eq!(call.description, "About the offer");
+        assert_eq!(call.priority, Priority::High);
+        assert!(call.completed);
+        assert_eq!(call.date, TaskDate(NaiveDate::from_ymd_opt(2026, 10, 12)));
+        assert_eq!(
+            call.due_time,
+            Some(DueTime {
+                time: at(9, 30),
+                timezone: Some(Tz::Europe__Rome)
+            })
+        );
+
+        let (_, renew) = &imported[1];
+        assert_eq!(renew.priority, Priority::Medium);
+        assert!(!renew.completed);
+        assert_eq!(renew.date, TaskDate(NaiveDate::from_ymd_opt(2026, 11, 1)));
+        assert_eq!(renew.due_time, None);
+
+        let (uid, rent) = &imported[2];
+        assert_eq!(*uid, None);
+        assert_eq!(rent.priority, Priority::Low);
+        assert_eq!(
+            rent.due_time,
+            Some(DueTime {
+                time: at(17, 0),
+                timezone: Some(Tz::UTC)
+            })
+        );
+    }
+
+    #[test]
+    fn exported_tasks_are_imported_back() {
+        let task = Task {
+            id: 7,
+            title: "Report".to_string(),
+            description: "Q3 numbers".to_string(),
+            priority: Priority::Medium,
+            date: TaskDate(NaiveDate::from_ymd_opt(2026, 10, 12)),
+            due_time: Some(DueTime {
+                time: at(18, 0),
+                timezone: Some(Tz::Europe__Rome),
+            }),
+            ..Task::default()
+        };
+        let ics =
+            export_tasks_to_icalendar("Task Rustler", DATABASE_ID, std::slice::from_ref(&task))
+                .to_string();
+        let path = temp_file("roundtrip.ics", &ics);
+        let imported = import_tasks_from_icalendar(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        let (uid, back) = &imported[0];
+        assert_eq!(uid.as_deref(), Some(task_uid(DATABASE_ID, 7).as_str()));
+        assert_eq!(back.title, task.title);
+        assert_eq!(back.description, task.description);
+        assert_eq!(back.priority, task.priority);
+        assert_eq!(back.date, task.date);
+        assert_eq!(back.due_time, task.due_time);
+    }
+
+    #[test]
+    fn priorities_follow_rfc_5545_and_round_trip() {
+        let todos: String = [0, 1, 2, 3, 4, 5, 6, 9]
+            .iter()
+            .map(|p| format!("BEGIN:VTODO\r\nSUMMARY:p{p}\r\nPRIORITY:{p}\r\nEND:VTODO\r\n"))
+            .collect();
+        let path = temp_file(
+            "priorities.ics",
+            &format!("BEGIN:VCALENDAR\r\n{todos}END:VCALENDAR\r\n"),
+        );
+        let imported = import_tasks_from_icalendar(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        let priorities: Vec<Priority> = imported.into_iter().map(|(_, t)| t.priority).collect();
+        use Priority::*;
+        assert_eq!(
+            priorities,
+            vec![Low, High, High, High, High, Medium, Low, Low]
+        );
+
+        let tasks: Vec<Task> = [High, Medium, Low]
+            .into_iter()
+            .map(|priority| Task {
+                priority,
+                ..Task::default()
+            })
+            .collect();
+        let path = temp_file(
+            "priorities-back.ics",
+            &export_tasks_to_icalendar("Task Rustler", DATABASE_ID, &tasks).to_string(),
+        );
+        let imported = import_tasks_from_icalendar(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        let priorities: Vec<Priority> = imported.into_iter().map(|(_, t)| t.priority).collect();
+        assert_eq!(priorities, vec![High, Medium, Low]);
+    }
+
+    #[test]
+    fn importing_an_export_of_the_same_database_adds_nothing() {
+        let tasks = TasksService::default();
+        tasks.add_new_task(&Task::default()).unwrap();
+        let parent = tasks.add_new_task(&Task::default()).unwrap();
+        tasks.add_subtask(parent, &Task::default()).unwrap();
+        let path = temp_file(
+            "same-db.ics",
+            &export_tasks_to_icalendar(
+                "Task Rustler",
+                tasks.database_id(),
+                &tasks.get_all_tasks().unwrap(),
+            )
+            .to_string(),
+        );
+        let imported = import_tasks_from_icalendar(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(imported.len(), 3);
+        assert_eq!(tasks.import_tasks(&imported).unwrap(), 0);
+        assert_eq!(tasks.length().unwrap(), 3);
+    }
+
+    #[test]
+    fn importing_an_export_of_another_database_adds_its_tasks() {
+        let other = TasksService::default();
+        other.add_new_task(&Task::default()).unwrap();
+        let path = temp_file(
+            "other-db.ics",
+            &export_tasks_to_icalendar(
+                "Task Rustler",
+                other.database_id(),
+                &other.get_all_tasks().unwrap(),
+            )
+            .to_string(),
+        );
+        let imported = import_tasks_from_icalendar(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+
+        // A task with the same id is already there
+        let tasks = TasksService::default();
+        tasks.add_new_task(&Task::default()).unwrap();
+        assert_eq!(tasks.import_tasks(&imported).unwrap(), 1);
+        assert_eq!(tasks.import_tasks(&imported).unwrap(), 0);
+        assert_eq!(tasks.length().unwrap(), 2);
+    }
+
+    #[test]
+    fn tasks_imported_before_are_skipped() {
+        let path = temp_file("twice.ics", CALENDAR);
+        let imported = import_tasks_from_icalendar(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let tasks = TasksService::default();
+        assert_eq!(tasks.import_tasks(&imported).unwrap(), 3);
+        // Without a UID there is nothing to recognise the task by
+        assert_eq!(tasks.import_tasks(&imported).unwrap(), 1);
+        let all = tasks.get_all_tasks().unwrap();
+        assert_eq!(all.len(), 4);
+        assert!(
+            all.iter()
+                .find(|t| t.title == "Call Bob")
+                .unwrap()
+                .completed
+        );
+    }
+
+    #[test]
+    fn unreadable_files_are_errors() {
+        assert!(import_tasks_from_icalendar("/nonexistent/tasks.ics").is_err());
+        let path = temp_file("garbage.ics", "not a calendar");
+        assert!(import_tasks_from_icalendar(path.to_str().unwrap()).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod test {
+    use rusqlite::Connection;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use task_rustler::export::task_uid;
+    use task_rustler::migrations::{backup_path, migrate, schema_version, SCHEMA_VERSION};
+    use task_rustler::project::INBOX_ID;
+    use task_rustler::task::Priority;
+    use task_rustler::task_manager::TasksService;
+
+    fn fixtures() -> Vec<(u32, PathBuf)> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let mut fixtures: Vec<(u32, PathBuf)> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter_map(|path| {
+                let name = path.file_name()?.to_str()?;
+                let version = name.strip_prefix("tasks_v")?.strip_suffix(".sql")?;
+                Some((version.parse().ok()?, path))
+            })
+            .collect();
+        fixtures.sort();
+        fixtures
+    }
+
+    fn temp_db(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("taskrs-migrations-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn there_is_a_fixture_for_every_past_version() {
+        let versions: Vec<u32> = fixtures().into_iter().map(|(v, _)| v).collect();
+        assert_eq!(versions, (0..SCHEMA_VERSION).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn upgrade_fixtures_from_every_past_version() {
+        for (version, fixture) in fixtures() {
+            let path = temp_db(&format!("fixture_v{version}.db"));
+            let _ = fs::remove_file(&path);
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(&fs::read_to_string(&fixture).unwrap())
+                .unwrap();
+            assert_eq!(schema_version(&conn).unwrap(), version);
+            drop(conn);
+
+            let db_path = path.to_str().unwrap();
+            let tasks = TasksService::new(db_path.to_string()).unwrap();
+            let all = tasks.get_all_tasks().unwrap();
+            assert_eq!(all.len(), 3, "fixture v{version}");
+            assert_eq!(all[0].title, "Pay rent");
+            assert_eq!(all[0].priority, Priority::High);
+            assert_eq!(String::try_from(all[0].date.clone()).unwrap(), "01-11-2024");
+            assert!(all[1].completed);
+            assert!(all[1].date.0.is_none());
+            assert!(all.iter().all(|t| t.project_id == INBOX_ID));
+            assert!(all.iter().all(|t| t.parent_id.is_none()));
+            assert!(all
+                .iter()
+                .all(|t| t.uid == Some(task_uid(tasks.database_id(), t.id))));
+            drop(tasks);
+
+            let conn = Connection::open(&path).unwrap();
+            assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+            let stored: String = conn
+                .query_row("SELECT end_date FROM tasks WHERE id = 1", [], |r| r.get(0))
+                .unwrap();
+            assert_eq!(stored, "2024-11-01");
+            // One backup per step, the first one is the fixture as it was.
+            for v in version..SCHEMA_VERSION {
+                let backup = Connection::open(backup_path(db_path, v)).unwrap();
+                assert_eq!(schema_version(&backup).unwrap(), v);
+                drop(backup);
+                fs::remove_file(backup_path(db_path, v)).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn new_database_is_created_at_the_latest_version_without_backup() {
+        let path = temp_db("new.db");
+        let _ = fs::remove_file(&path);
+        let db_path = path.to_str().unwrap().to_string();
+        TasksService::new(db_path.clone()).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(!Path::new(&backup_path(&db_path, 0)).exists());
+        // Nothing to do the second time.
+        TasksService::new(db_path).unwrap();
+    }
+
+    #[test]
+    fn newer_database_is_refused() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        assert!(migrate(&mut conn).is_err());
+    }
+}
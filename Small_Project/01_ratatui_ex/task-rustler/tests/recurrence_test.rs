@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, Weekday};
+    use task_rustler::date::TaskDate;
+    use task_rustler::recurrence::Recurrence;
+    use task_rustler::task::Task;
+
+    fn date(d: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for rule in ["daily", "weekly mon,fri", "monthly 15", "every 3 days"] {
+            assert_eq!(rule.parse::<Recurrence>().unwrap().to_string(), rule);
+        }
+        assert_eq!(
+            " Weekly FRI,monday,fri".parse::<Recurrence>().unwrap(),
+            Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri])
+        );
+        assert_eq!(
+            "every 1 day".parse::<Recurrence>().unwrap(),
+            Recurrence::EveryNDays(1)
+        );
+        for invalid in [
+            "",
+            "weekly",
+            "weekly xyz",
+            "monthly 32",
+            "monthly 0",
+            "every 0 days",
+            "hourly",
+        ] {
+            assert!(invalid.parse::<Recurrence>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn next_date() {
+        assert_eq!(
+            Recurrence::Daily.next_date(date("2024-12-31")),
+            date("2025-01-01")
+        );
+        assert_eq!(
+            Recurrence::EveryNDays(10).next_date(date("2024-02-25")),
+            date("2024-03-06")
+        );
+        // 2024-10-18 is a Friday
+        let weekly = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]);
+        assert_eq!(weekly.next_date(date("2024-10-18")), date("2024-10-21"));
+        assert_eq!(weekly.next_date(date("2024-10-21")), date("2024-10-25"));
+        assert_eq!(weekly.next_date(date("2024-10-22")), date("2024-10-25"));
+        let friday = Recurrence::Weekly(vec![Weekday::Fri]);
+        assert_eq!(friday.next_date(date("2024-10-18")), date("2024-10-25"));
+    }
+
+    #[test]
+    fn next_date_monthly_falls_back_to_the_end_of_short_months() {
+        let monthly = Recurrence::Monthly(15);
+        assert_eq!(monthly.next_date(date("2024-10-01")), date("2024-10-15"));
+        assert_eq!(monthly.next_date(date("2024-10-15")), date("2024-11-15"));
+        assert_eq!(monthly.next_date(date("2024-12-20")), date("2025-01-15"));
+        let monthly = Recurrence::Monthly(31);
+        assert_eq!(monthly.next_date(date("2024-01-31")), date("2024-02-29"));
+        assert_eq!(monthly.next_date(date("2024-02-29")), date("2024-03-31"));
+        assert_eq!(monthly.next_date(date("2024-04-10")), date("2024-04-30"));
+    }
+
+    #[test]
+    fn rrule() {
+        assert_eq!(Recurrence::Daily.to_rrule(), "FREQ=DAILY");
+        assert_eq!(
+            Recurrence::EveryNDays(3).to_rrule(),
+            "FREQ=DAILY;INTERVAL=3"
+        );
+        assert_eq!(
+            Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri]).to_rrule(),
+            "FREQ=WEEKLY;BYDAY=MO,FR"
+        );
+        assert_eq!(
+            Recurrence::Monthly(15).to_rrule(),
+            "FREQ=MONTHLY;BYMONTHDAY=15"
+        );
+        assert_eq!(
+            Recurrence::Monthly(30).to_rrule(),
+            "FREQ=MONTHLY;BYMONTHDAY=28,29,30;BYSETPOS=-1"
+        );
+    }
+
+    #[test]
+    fn next_occurrence() {
+        let today = date("2024-10-18");
+        assert_eq!(Task::default().next_occurrence(today), None);
+        let task = Task {
+            id: 4,
+            completed: true,
+            date: TaskDate(Some(date("2024-10-01"))),
+            recurrence: Some(Recurrence::Monthly(1)),
+            tags: vec!["bills".to_string()],
+            ..Task::default()
+        };
+        let next = task.next_occurrence(today).unwrap();
+        assert_eq!(next.date, TaskDate(Some(date("2024-11-01"))));
+        assert!(!next.completed);
+        assert_eq!(next.tags, task.tags);
+        assert_eq!(next.recurrence, task.recurrence);
+        // Without a date the next occurrence is counted from today
+        let task = Task {
+            date: TaskDate(None),
+            ..task
+        };
+        assert_eq!(
+            task.next_occurrence(today).unwrap().date,
+            TaskDate(Some(date("2024-11-01")))
+        );
+    }
+}
@@ -1,6 +1,11 @@
 #[cfg(test)]
 mod test {
+    use chrono::NaiveDate;
     use task_rustler::date::TaskDate;
+    use task_rustler::error::TaskError;
+    use task_rustler::export::task_uid;
+    use task_rustler::project::INBOX_ID;
+    use task_rustler::recurrence::Recurrence;
     use task_rustler::task::{Priority, Task};
     use task_rustler::task_manager::SortOrder;
     use task_rustler::task_manager::TasksService;
@@ -15,6 +20,12 @@ mod test {
                 completed: false,
                 priority: Priority::Low,
                 date: TaskDate::try_from("19-11-1976".to_string()).unwrap(),
+                tags: vec![],
+                project_id: INBOX_ID,
+                parent_id: None,
+                recurrence: None,
+                due_time: None,
+                uid: None,
             },
             Task {
                 id: 2,
@@ -23,6 +34,12 @@ mod test {
                 completed: false,
                 priority: Priority::Medium,
                 date: TaskDate(None),
+                tags: vec![],
+                project_id: INBOX_ID,
+                parent_id: None,
+                recurrence: None,
+                due_time: None,
+                uid: None,
             },
             Task {
                 id: 3,
@@ -31,29 +48,35 @@ mod test {
                 completed: false,
                 priority: Priority::High,
                 date: TaskDate::try_from("19-11-2024".to_string()).unwrap(),
+                tags: vec![],
+                project_id: INBOX_ID,
+                parent_id: None,
+                recurrence: None,
+                due_time: None,
+                uid: None,
             },
         ];
         for t in tasks_to_add {
-            tasks.add_new_task(&t)
+            tasks.add_new_task(&t).unwrap();
         }
         tasks
     }
 
     fn teardown(t: &TasksService) {
-        t.delete_all_tasks();
+        t.delete_all_tasks().unwrap();
     }
 
     #[test]
     fn get_all_tasks() {
         let t = setup();
-        assert_eq!(t.length(), 3);
+        assert_eq!(t.length().unwrap(), 3);
         teardown(&t);
     }
     #[test]
     fn should_return_task_if_id_exists() {
         let t = setup();
-        t.add_new_task(&Task::default());
-        let task = t.get_task(4).unwrap();
+        t.add_new_task(&Task::default()).unwrap();
+        let task = t.get_task(4).unwrap().unwrap();
         assert_eq!(task.id, 4);
         assert_eq!(task.description, "Test task description");
         assert_eq!(task.completed, false);
@@ -62,31 +85,31 @@ mod test {
     #[test]
     fn should_return_none_if_task_is_not_found() {
         let t = setup();
-        let task = t.get_task(100);
+        let task = t.get_task(100).unwrap();
         assert_eq!(task.is_none(), true);
     }
     #[test]
     fn set_completed_should_return_1_if_task_exists_0_otherwise() {
         let t = setup();
-        let num_tasks_completed = t.toggle_task_status(1, true);
+        let num_tasks_completed = t.toggle_task_status(1, true).unwrap();
         assert_eq!(num_tasks_completed, 1);
-        let num_tasks_completed = t.toggle_task_status(100, true);
+        let num_tasks_completed = t.toggle_task_status(100, true).unwrap();
         assert_eq!(num_tasks_completed, 0);
     }
 
     #[test]
     fn delete_task_should_return_1_if_task_exists_0_otherwise() {
         let t = setup();
-        let num_task_removed = t.delete_task(2);
+        let num_task_removed = t.delete_task(2).unwrap();
         assert_eq!(num_task_removed, 1);
-        let num_task_removed = t.delete_task(100);
+        let num_task_removed = t.delete_task(100).unwrap();
         assert_eq!(num_task_removed, 0);
     }
 
     #[test]
     fn get_all_the_task_sorted_by_highest_priority() {
         let t = setup();
-        let tasks = t.get_all_tasks_sorted_by_priority(SortOrder::High);
+        let tasks = t.get_all_tasks_sorted_by_priority(SortOrder::High).unwrap();
         assert_eq!(
             tasks[0],
             Task {
@@ -96,6 +119,12 @@ mod test {
                 completed: false,
                 priority: Priority::High,
                 date: TaskDate::try_from("19-11-2024".to_string()).unwrap(),
+                tags: vec![],
+                project_id: INBOX_ID,
+                parent_id: None,
+                recurrence: None,
+                due_time: None,
+                uid: Some(task_uid(t.database_id(), 3)),
             }
         );
     }
@@ -103,7 +132,7 @@ mod test {
     #[test]
     fn get_all_the_task_sorted_by_lowest_priority() {
         let t = setup();
-        let tasks = t.get_all_tasks_sorted_by_priority(SortOrder::Low);
+        let tasks = t.get_all_tasks_sorted_by_priority(SortOrder::Low).unwrap();
         assert_eq!(
             tasks[0],
             Task {
@@ -113,6 +142,12 @@ mod test {
                 completed: false,
                 priority: Priority::Low,
                 date: TaskDate::try_from("19-11-1976".to_string()).unwrap(),
+                tags: vec![],
+                project_id: INBOX_ID,
+                parent_id: None,
+                recurrence: None,
+                due_time: None,
+                uid: Some(task_uid(t.database_id(), 1)),
             }
         );
     }
@@ -120,7 +155,7 @@ mod test {
     #[test]
     fn get_all_tasks_sorted_by_newest() {
         let t = setup();
-        let tasks = t.get_all_tasks_sorted_by_date(SortOrder::High);
+        let tasks = t.get_all_tasks_sorted_by_date(SortOrder::High).unwrap();
         assert_eq!(
             tasks[0],
             Task {
@@ -130,6 +165,12 @@ mod test {
                 completed: false,
                 priority: Priority::High,
                 date: TaskDate::try_from("19-11-2024".to_string()).unwrap(),
+                tags: vec![],
+                project_id: INBOX_ID,
+                parent_id: None,
+                recurrence: None,
+                due_time: None,
+                uid: Some(task_uid(t.database_id(), 3)),
             }
         );
     }
@@ -137,17 +178,208 @@ mod test {
     #[test]
     fn get_all_tasks_sorted_by_oldest() {
         let t = setup();
-        let tasks = t.get_all_tasks_sorted_by_date(SortOrder::Low);
+        let tasks = t.get_all_tasks_sorted_by_date(SortOrder::Low).unwrap();
         assert_eq!(
             tasks[0],
             Task {
-                id: 2,
-                title: "My second task title".to_string(),
-                description: "Second task".to_string(),
+                id: 1,
+                title: "My first task title".to_string(),
+                description: "First task".to_string(),
                 completed: false,
-                priority: Priority::Medium,
-                date: TaskDate(None),
+                priority: Priority::Low,
+                date: TaskDate::try_from("19-11-1976".to_string()).unwrap(),
+                tags: vec![],
+                project_id: INBOX_ID,
+                parent_id: None,
+                recurrence: None,
+                due_time: None,
+                uid: Some(task_uid(t.database_id(), 1)),
             }
         );
     }
+
+    #[test]
+    fn tasks_without_date_sort_last_in_both_directions() {
+        let t = setup();
+        t.add_new_task(&Task {
+            date: TaskDate::try_from("05-12-2024".to_string()).unwrap(),
+            ..Task::default()
+        })
+        .unwrap();
+        for sort in [SortOrder::High, SortOrder::Low] {
+            let tasks = t.get_all_tasks_sorted_by_date(sort).unwrap();
+            assert_eq!(tasks[3].id, 2);
+        }
+        // Chronological, not by day of the month.
+        let ids: Vec<i32> = t
+            .get_all_tasks_sorted_by_date(SortOrder::High)
+            .unwrap()
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![4, 3, 1, 2]);
+    }
+
+    #[test]
+    fn invalid_rows_are_errors_instead_of_panics() {
+        let path = std::env::temp_dir().join(format!("taskrs-invalid-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let t = TasksService::new(path.to_str().unwrap().to_string()).unwrap();
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute(
+            "INSERT INTO tasks (title, description, completed, priority, end_date) VALUES ('t', '', 0, 7, NULL)",
+            [],
+        )
+        .unwrap();
+        assert!(matches!(t.get_all_tasks(), Err(TaskError::Db(_))));
+        conn.execute("UPDATE tasks SET priority = 1, end_date = 'someday'", [])
+            .unwrap();
+        assert!(matches!(t.get_task(1), Err(TaskError::Db(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unopenable_database_is_an_error() {
+        let res = TasksService::new(std::env::temp_dir().to_str().unwrap().to_string());
+        assert!(matches!(res, Err(TaskError::Open { .. })));
+    }
+
+    #[test]
+    fn tags_are_many_to_many() {
+        let t = setup();
+        let id = t
+            .add_new_task(&Task {
+                tags: vec!["work".to_string(), "home".to_string()],
+                ..Task::default()
+            })
+            .unwrap();
+        assert_eq!(t.get_task(id).unwrap().unwrap().tags, vec!["home", "work"]);
+        assert_eq!(t.add_tag(1, "work").unwrap(), 1);
+        assert_eq!(t.add_tag(1, "Work").unwrap(), 0);
+        assert!(matches!(
+            t.add_tag(100, "work"),
+            Err(TaskError::NotFound(100))
+        ));
+
+        let ids: Vec<i32> = t
+            .tasks_with_tag("work")
+            .unwrap()
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![1, id]);
+        assert_eq!(t.all_tags().unwrap(), vec!["home", "work"]);
+
+        assert_eq!(t.remove_tag(id, "home").unwrap(), 1);
+        assert_eq!(t.remove_tag(id, "home").unwrap(), 0);
+        // Nobody uses "home" anymore.
+        assert_eq!(t.all_tags().unwrap(), vec!["work"]);
+
+        let mut task = t.get_task(1).unwrap().unwrap();
+        task.tags = vec!["errands".to_string()];
+        t.update_task(&task).unwrap();
+        assert_eq!(t.tasks_with_tag("work").unwrap().len(), 1);
+        t.delete_task(id).unwrap();
+        assert_eq!(t.all_tags().unwrap(), vec!["errands"]);
+    }
+
+    #[test]
+    fn projects_hold_tasks() {
+        let t = setup();
+        let work = t.add_project("Work").unwrap();
+        assert!(matches!(
+            t.add_project(" work "),
+            Err(TaskError::ProjectExists(_))
+        ));
+        assert_eq!(t.move_task(2, work).unwrap(), 1);
+        assert_eq!(t.move_task(42, work).unwrap(), 0);
+
+        let projects: Vec<(String, usize)> = t
+            .projects()
+            .unwrap()
+            .into_iter()
+            .map(|(p, count)| (p.name, count))
+            .collect();
+        assert_eq!(
+            projects,
+            vec![("Inbox".to_string(), 2), ("Work".to_string(), 1)]
+        );
+        assert_eq!(t.project_by_name("WORK").unwrap().id, work);
+        assert!(matches!(
+            t.project_by_name("Home"),
+            Err(TaskError::ProjectNotFound(_))
+        ));
+        let ids: Vec<i32> = t
+            .tasks_in_project(INBOX_ID)
+            .unwrap()
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![3, 1]);
+        assert_eq!(t.get_task(2).unwrap().unwrap().project_id, work);
+    }
+
+    #[test]
+    fn subtasks() {
+        let t = setup();
+        let work = t.add_project("Work").unwrap();
+        t.move_task(1, work).unwrap();
+        let step = t.add_subtask(1, &Task::default()).unwrap();
+        let sub_step = t.add_subtask(step, &Task::default()).unwrap();
+        t.add_subtask(1, &Task::default()).unwrap();
+        assert!(matches!(
+            t.add_subtask(42, &Task::default()),
+            Err(TaskError::NotFound(42))
+        ));
+
+        let task = t.get_task(sub_step).unwrap().unwrap();
+        assert_eq!(task.parent_id, Some(step));
+        assert_eq!(task.project_id, work);
+        t.toggle_task_status(step, true).unwrap();
+        let counts = t.subtask_counts().unwrap();
+        assert_eq!(counts[&1], (1, 2));
+        assert_eq!(counts[&step], (0, 1));
+        assert_eq!(counts.len(), 2);
+
+        // Moving or completing a task takes its subtasks along
+        assert_eq!(t.move_task(1, INBOX_ID).unwrap(), 4);
+        assert_eq!(t.get_task(sub_step).unwrap().unwrap().project_id, INBOX_ID);
+        assert_eq!(t.set_status_with_subtasks(1, true).unwrap(), 4);
+        assert_eq!(t.subtask_counts().unwrap()[&1], (2, 2));
+
+        t.delete_task(1).unwrap();
+        assert!(t.get_task(sub_step).unwrap().is_none());
+        assert_eq!(t.length().unwrap(), 2);
+    }
+
+    #[test]
+    fn completing_a_recurring_task_adds_the_next_occurrence() {
+        let t = setup();
+        let today = NaiveDate::from_ymd_opt(2024, 10, 18).unwrap();
+        let id = t
+            .add_new_task(&Task {
+                date: TaskDate(Some(NaiveDate::from_ymd_opt(2024, 10, 14).unwrap())),
+                recurrence: Some(Recurrence::EveryNDays(7)),
+                tags: vec!["report".to_string()],
+                ..Task::default()
+            })
+            .unwrap();
+        let next = t.complete_task(id, today).unwrap().unwrap();
+
+        let done = t.get_task(id).unwrap().unwrap();
+        assert!(done.completed);
+        assert_eq!(done.recurrence, None);
+        let next = t.get_task(next).unwrap().unwrap();
+        assert!(!next.completed);
+        assert_eq!(next.date.0, NaiveDate::from_ymd_opt(2024, 10, 21));
+        assert_eq!(next.recurrence, Some(Recurrence::EveryNDays(7)));
+        assert_eq!(next.tags, vec!["report"]);
+
+        assert_eq!(t.complete_task(1, today).unwrap(), None);
+        assert!(t.get_task(1).unwrap().unwrap().completed);
+        assert!(matches!(
+            t.complete_task(42, today),
+            Err(TaskError::NotFound(42))
+        ));
+    }
 }
@@ -0,0 +1,106 @@
+//! Lock-free collections against their `Mutex` counterparts, 4 threads
+//! hammering the same structure.
+
+use std::{collections::VecDeque, sync::Mutex, thread};
+
+use a01_crossbeam_injector_stealer_worker::collections::{ListSet, MsQueue, TreiberStack};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const THREADS: usize = 4;
+const OPS: usize = 10_000;
+
+/// Run `op(thread, i)` `OPS` times on each of `THREADS` threads.
+fn hammer(op: impl Fn(usize, usize) + Sync) {
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            let op = &op;
+            s.spawn(move || (0..OPS).for_each(|i| op(t, i)));
+        }
+    });
+}
+
+fn stack(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stack push/pop");
+    group.bench_function("TreiberStack", |b| {
+        let stack = TreiberStack::new();
+        b.iter(|| {
+            hammer(|_, i| {
+                stack.push(i);
+                stack.pop();
+            })
+        });
+    });
+    group.bench_function("Mutex<Vec>", |b| {
+        let stack = Mutex::new(Vec::new());
+        b.iter(|| {
+            hammer(|_, i| {
+                stack.lock().unwrap().push(i);
+                stack.lock().unwrap().pop();
+            })
+        });
+    });
+    group.finish();
+}
+
+fn queue(c: &mut Criterion) {
+    let mut group = c.benchmark_group("queue push/pop");
+    group.bench_function("MsQueue", |b| {
+        let queue = MsQueue::new();
+        b.iter(|| {
+            hammer(|_, i| {
+                queue.push(i);
+                queue.pop();
+            })
+        });
+    });
+    group.bench_function("Mutex<VecDeque>", |b| {
+        let queue = Mutex::new(VecDeque::new());
+        b.iter(|| {
+            hammer(|_, i| {
+                queue.lock().unwrap().push_back(i);
+                queue.lock().unwrap().pop_front();
+            })
+        });
+    });
+    group.finish();
+}
+
+fn set(c: &mut Criterion) {
+    // Mostly lookups on a small set, where a list is a reasonable choice.
+    const KEYS: usize = 64;
+    let mut group = c.benchmark_group("set 90% contains");
+    group.bench_function("ListSet", |b| {
+        let set = ListSet::new();
+        (0..KEYS).step_by(2).for_each(|k| {
+            set.insert(k);
+        });
+        b.iter(|| {
+            hammer(|t, i| {
+                let k = (i * 7 + t) % KEYS;
+                match i % 20 {
+                    0 => drop(set.insert(k)),
+                    1 => drop(set.remove(&k)),
+                    _ => drop(set.contains(&k)),
+                }
+            })
+        });
+    });
+    group.bench_function("Mutex<Vec> sorted", |b| {
+        let set = Mutex::new((0..KEYS).step_by(2).collect::<Vec<_>>());
+        b.iter(|| {
+            hammer(|t, i| {
+                let k = (i * 7 + t) % KEYS;
+                let mut set = set.lock().unwrap();
+                match (i % 20, set.binary_search(&k)) {
+                    (0, Err(pos)) => set.insert(pos, k),
+                    (1, Ok(pos)) => drop(set.remove(pos)),
+                    _ => {}
+                }
+            })
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, stack, queue, set);
+criterion_main!(benches);
@@ -0,0 +1,254 @@
+use std::{cmp, sync::atomic::Ordering};
+
+use crossbeam::epoch::{self, Atomic, Guard, Owned, Shared};
+
+/// Sorted set on a Harris-Michael linked list.
+///
+/// Removing a key first marks its node by tagging the node's `next` pointer,
+/// which stops inserts behind it, then unlinks it. Searches unlink any
+/// marked node they walk over.
+///
+/// ```
+/// use a01_crossbeam_injector_stealer_worker::collections::ListSet;
+///
+/// let set = ListSet::new();
+/// assert!(set.insert(3));
+/// assert!(!set.insert(3));
+/// assert!(set.contains(&3));
+/// assert!(set.remove(&3));
+/// ```
+pub struct ListSet<T> {
+    head: Atomic<Node<T>>,
+}
+
+struct Node<T> {
+    key: T,
+    next: Atomic<Node<T>>,
+}
+
+/// Tag of `next` on a node that was removed from the set.
+const MARKED: usize = 1;
+
+impl<T: Ord> ListSet<T> {
+    pub fn new() -> Self {
+        Self {
+            head: Atomic::null(),
+        }
+    }
+
+    /// `false` if the key was already there.
+    pub fn insert(&self, key: T) -> bool {
+        let guard = epoch::pin();
+        let mut node = Owned::new(Node {
+            key,
+            next: Atomic::null(),
+        });
+        loop {
+            let (prev, curr, found) = self.find(&node.key, &guard);
+            if found {
+                return false;
+            }
+            node.next.store(curr, Ordering::Relaxed);
+            match prev.compare_exchange(curr, node, Ordering::Release, Ordering::Relaxed, &guard) {
+                Ok(_) => return true,
+                Err(e) => node = e.new,
+            }
+        }
+    }
+
+    /// `false` if the key wasn't there.
+    pub fn remove(&self, key: &T) -> bool {
+        let guard = epoch::pin();
+        loop {
+            let (prev, curr, found) = self.find(key, &guard);
+            if !found {
+                return false;
+            }
+            let c = unsafe { curr.deref() };
+            let next = c.next.load(Ordering::Acquire, &guard);
+            if next.tag() == MARKED
+                || c.next
+                    .compare_exchange(
+                        next,
+                        next.with_tag(MARKED),
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                        &guard,
+                    )
+                    .is_err()
+            {
+                continue;
+            }
+            // The key is gone from here on. If unlinking fails, the next
+            // search going past the node will do it.
+            if prev
+                .compare_exchange(curr, next, Ordering::Release, Ordering::Relaxed, &guard)
+                .is_ok()
+            {
+                unsafe { guard.defer_destroy(curr) };
+            }
+            return true;
+        }
+    }
+
+    /// Read-only walk, marked nodes are skipped but left in place.
+    pub fn contains(&self, key: &T) -> bool {
+        let guard = epoch::pin();
+        let mut curr = self.head.load(Ordering::Acquire, &guard);
+        while let Some(c) = unsafe { curr.as_ref() } {
+            let next = c.next.load(Ordering::Acquire, &guard);
+            match c.key.cmp(key) {
+                cmp::Ordering::Less => curr = next.with_tag(0),
+                cmp::Ordering::Equal => return next.tag() != MARKED,
+                cmp::Ordering::Greater => return false,
+            }
+        }
+        false
+    }
+
+    /// Keys in the set, in order.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let guard = epoch::pin();
+        let mut keys = Vec::new();
+        let mut curr = self.head.load(Ordering::Acquire, &guard);
+        while let Some(c) = unsafe { curr.as_ref() } {
+            let next = c.next.load(Ordering::Acquire, &guard);
+            if next.tag() != MARKED {
+                keys.push(c.key.clone());
+            }
+            curr = next.with_tag(0);
+        }
+        keys
+    }
+
+    /// Link to the first node with a key not below `key`, and that node.
+    fn find<'g>(
+        &'g self,
+        key: &T,
+        guard: &'g Guard,
+    ) -> (&'g Atomic<Node<T>>, Shared<'g, Node<T>>, bool) {
+        'retry: loop {
+            let mut prev = &self.head;
+            let mut curr = prev.load(Ordering::Acquire, guard);
+            while let Some(c) = unsafe { curr.as_ref() } {
+                let next = c.next.load(Ordering::Acquire, guard);
+                if next.tag() == MARKED {
+                    // Fails if `prev` was marked or changed meanwhile.
+                    match prev.compare_exchange(
+                        curr,
+                        next.with_tag(0),
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                        guard,
+                    ) {
+                        Ok(_) => {
+                            unsafe { guard.defer_destroy(curr) };
+                            curr = next.with_tag(0);
+                        }
+                        Err(_) => continue 'retry,
+                    }
+                    continue;
+                }
+                match c.key.cmp(key) {
+                    cmp::Ordering::Less => {
+                        prev = &c.next;
+                        curr = next;
+                    }
+                    cmp::Ordering::Equal => return (prev, curr, true),
+                    cmp::Ordering::Greater => return (prev, curr, false),
+                }
+            }
+            return (prev, curr, false);
+        }
+    }
+}
+
+impl<T: Ord> Default for ListSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ListSet<T> {
+    fn drop(&mut self) {
+        // `&mut self`: no other thread can hold a reference into the list.
+        unsafe {
+            let guard = epoch::unprotected();
+            let mut curr = self.head.load(Ordering::Relaxed, guard);
+            while !curr.is_null() {
+                let node = curr.into_owned();
+                curr = node.next.load(Ordering::Relaxed, guard).with_tag(0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::Barrier, thread};
+
+    #[test]
+    fn keeps_keys_sorted_and_unique() {
+        let set = ListSet::new();
+        for k in [5, 1, 9, 3, 5, 7, 1] {
+            set.insert(k);
+        }
+        assert_eq!(set.to_vec(), vec![1, 3, 5, 7, 9]);
+        assert!(set.remove(&5));
+        assert!(!set.remove(&5));
+        assert!(!set.contains(&5));
+        assert!(set.contains(&9));
+        assert_eq!(set.to_vec(), vec![1, 3, 7, 9]);
+    }
+
+    #[test]
+    fn concurrent_inserts_and_removes() {
+        let set = ListSet::new();
+        let inserted = Barrier::new(4);
+        thread::scope(|s| {
+            for t in 0..4 {
+                let (set, inserted) = (&set, &inserted);
+                s.spawn(move || {
+                    // Every thread inserts all keys, only one insert of each wins.
+                    for k in 0..500 {
+                        set.insert(k);
+                    }
+                    inserted.wait();
+                    // Odd keys are removed by the thread owning them.
+                    for k in (0..500).filter(|k| k % 2 == 1 && k % 4 == t) {
+                        assert!(set.remove(&k));
+                    }
+                });
+            }
+        });
+        assert_eq!(set.to_vec(), (0..500).step_by(2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn contains_during_churn() {
+        let set = ListSet::new();
+        set.insert(1_000);
+        thread::scope(|s| {
+            for t in 0..3 {
+                let set = &set;
+                s.spawn(move || {
+                    for round in 0..200 {
+                        let k = t * 200 + round;
+                        set.insert(k);
+                        assert!(set.remove(&k));
+                    }
+                });
+            }
+            s.spawn(|| {
+                for _ in 0..2_000 {
+                    assert!(set.contains(&1_000));
+                }
+            });
+        });
+        assert_eq!(set.to_vec(), vec![1_000]);
+    }
+}
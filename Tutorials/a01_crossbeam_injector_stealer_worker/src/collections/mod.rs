@@ -0,0 +1,12 @@
+//! Lock-free collections built on `crossbeam::epoch`.
+//!
+//! Removed nodes are handed to the epoch collector with `defer_destroy` and
+//! freed once no pinned thread can still see them.
+
+mod list;
+mod queue;
+mod stack;
+
+pub use list::ListSet;
+pub use queue::MsQueue;
+pub use stack::TreiberStack;
@@ -0,0 +1,229 @@
+use std::{mem::MaybeUninit, sync::atomic::Ordering};
+
+use crossbeam::{
+    epoch::{self, Atomic, Owned, Shared},
+    utils::CachePadded,
+};
+
+/// Michael-Scott queue: a linked list with a sentinel node at the head.
+///
+/// Producers and consumers work on different ends, so `head` and `tail` get
+/// their own cache lines.
+///
+/// ```
+/// use a01_crossbeam_injector_stealer_worker::collections::MsQueue;
+///
+/// let queue = MsQueue::new();
+/// queue.push(1);
+/// queue.push(2);
+/// assert_eq!(queue.pop(), Some(1));
+/// ```
+pub struct MsQueue<T> {
+    head: CachePadded<Atomic<Node<T>>>,
+    tail: CachePadded<Atomic<Node<T>>>,
+}
+
+struct Node<T> {
+    // Uninit in the sentinel, and in any node once its value was popped.
+    data: MaybeUninit<T>,
+    next: Atomic<Node<T>>,
+}
+
+unsafe impl<T: Send> Send for MsQueue<T> {}
+unsafe impl<T: Send> Sync for MsQueue<T> {}
+
+impl<T> MsQueue<T> {
+    pub fn new() -> Self {
+        let queue = Self {
+            head: CachePadded::new(Atomic::null()),
+            tail: CachePadded::new(Atomic::null()),
+        };
+        let sentinel = Owned::new(Node {
+            data: MaybeUninit::uninit(),
+            next: Atomic::null(),
+        });
+        // Not shared with anyone yet.
+        unsafe {
+            let sentinel = sentinel.into_shared(epoch::unprotected());
+            queue.head.store(sentinel, Ordering::Relaxed);
+            queue.tail.store(sentinel, Ordering::Relaxed);
+        }
+        queue
+    }
+
+    pub fn push(&self, data: T) {
+        let guard = epoch::pin();
+        let node = Owned::new(Node {
+            data: MaybeUninit::new(data),
+            next: Atomic::null(),
+        })
+        .into_shared(&guard);
+        loop {
+            let tail = self.tail.load(Ordering::Acquire, &guard);
+            let t = unsafe { tail.deref() };
+            let next = t.next.load(Ordering::Acquire, &guard);
+            if !next.is_null() {
+                // `tail` is lagging behind, help move it before retrying.
+                let _ = self.tail.compare_exchange(
+                    tail,
+                    next,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                    &guard,
+                );
+                continue;
+            }
+            if t.next
+                .compare_exchange(
+                    Shared::null(),
+                    node,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                    &guard,
+                )
+                .is_ok()
+            {
+                let _ = self.tail.compare_exchange(
+                    tail,
+                    node,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                    &guard,
+                );
+                return;
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Acquire, &guard);
+            let h = unsafe { head.deref() };
+            let next = h.next.load(Ordering::Acquire, &guard);
+            let n = unsafe { next.as_ref() }?;
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::Release, Ordering::Relaxed, &guard)
+                .is_ok()
+            {
+                // Never let `tail` point at a node that is about to be freed.
+                let tail = self.tail.load(Ordering::Relaxed, &guard);
+                if head == tail {
+                    let _ = self.tail.compare_exchange(
+                        tail,
+                        next,
+                        Ordering::Release,
+                        Ordering::Relaxed,
+                        &guard,
+                    );
+                }
+                // `next` is the new sentinel, its value is ours to take.
+                unsafe {
+                    guard.defer_destroy(head);
+                    return Some(n.data.assume_init_read());
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let guard = epoch::pin();
+        let head = self.head.load(Ordering::Acquire, &guard);
+        unsafe { head.deref() }
+            .next
+            .load(Ordering::Acquire, &guard)
+            .is_null()
+    }
+}
+
+impl<T> Default for MsQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for MsQueue<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+        unsafe {
+            let sentinel = self.head.load(Ordering::Relaxed, epoch::unprotected());
+            drop(sentinel.into_owned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{atomic::AtomicUsize, Arc, Mutex},
+        thread,
+    };
+
+    #[test]
+    fn fifo_order() {
+        let queue = MsQueue::new();
+        assert!(queue.is_empty());
+        for i in 0..5 {
+            queue.push(i);
+        }
+        let popped: Vec<i32> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(popped, vec![0, 1, 2, 3, 4]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn per_producer_order_is_kept() {
+        let queue = MsQueue::new();
+        let popped = Mutex::new(Vec::new());
+        thread::scope(|s| {
+            for t in 0..4 {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        queue.push((t, i));
+                    }
+                });
+            }
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut mine = Vec::new();
+                    while mine.len() < 1000 {
+                        if let Some(x) = queue.pop() {
+                            mine.push(x);
+                        }
+                    }
+                    popped.lock().unwrap().push(mine);
+                });
+            }
+        });
+        let popped = popped.into_inner().unwrap();
+        // Each consumer sees every producer's items in push order.
+        for mine in &popped {
+            for t in 0..4 {
+                let seen: Vec<i32> = mine.iter().filter(|x| x.0 == t).map(|x| x.1).collect();
+                assert!(seen.windows(2).all(|w| w[0] < w[1]));
+            }
+        }
+        assert_eq!(popped.iter().map(Vec::len).sum::<usize>(), 4000);
+    }
+
+    #[test]
+    fn drops_remaining_items_once() {
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let drops = Arc::new(AtomicUsize::new(0));
+        let queue = MsQueue::new();
+        for _ in 0..10 {
+            queue.push(Counted(Arc::clone(&drops)));
+        }
+        drop(queue.pop());
+        drop(queue);
+        assert_eq!(drops.load(Ordering::SeqCst), 10);
+    }
+}
@@ -0,0 +1,162 @@
+use std::{mem::ManuallyDrop, ptr, sync::atomic::Ordering};
+
+use crossbeam::epoch::{self, Atomic, Owned};
+
+/// Treiber stack: a singly linked list whose head is swapped with CAS.
+///
+/// ```
+/// use a01_crossbeam_injector_stealer_worker::collections::TreiberStack;
+///
+/// let stack = TreiberStack::new();
+/// stack.push(1);
+/// stack.push(2);
+/// assert_eq!(stack.pop(), Some(2));
+/// ```
+pub struct TreiberStack<T> {
+    head: Atomic<Node<T>>,
+}
+
+struct Node<T> {
+    // Moved out by `pop`, the node itself is freed later by the collector.
+    data: ManuallyDrop<T>,
+    next: Atomic<Node<T>>,
+}
+
+unsafe impl<T: Send> Send for TreiberStack<T> {}
+unsafe impl<T: Send> Sync for TreiberStack<T> {}
+
+impl<T> TreiberStack<T> {
+    pub fn new() -> Self {
+        Self {
+            head: Atomic::null(),
+        }
+    }
+
+    pub fn push(&self, data: T) {
+        let mut node = Owned::new(Node {
+            data: ManuallyDrop::new(data),
+            next: Atomic::null(),
+        });
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Relaxed, &guard);
+            node.next.store(head, Ordering::Relaxed);
+            match self.head.compare_exchange(
+                head,
+                node,
+                Ordering::Release,
+                Ordering::Relaxed,
+                &guard,
+            ) {
+                Ok(_) => return,
+                Err(e) => node = e.new,
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Acquire, &guard);
+            let h = unsafe { head.as_ref() }?;
+            let next = h.next.load(Ordering::Relaxed, &guard);
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::Relaxed, Ordering::Relaxed, &guard)
+                .is_ok()
+            {
+                // We unlinked `head`, nobody else will read its data.
+                unsafe {
+                    guard.defer_destroy(head);
+                    return Some(ManuallyDrop::into_inner(ptr::read(&h.data)));
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let guard = epoch::pin();
+        self.head.load(Ordering::Acquire, &guard).is_null()
+    }
+}
+
+impl<T> Default for TreiberStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for TreiberStack<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{atomic::AtomicUsize, Arc, Mutex},
+        thread,
+    };
+
+    #[test]
+    fn lifo_order() {
+        let stack = TreiberStack::new();
+        assert!(stack.is_empty());
+        for i in 0..5 {
+            stack.push(i);
+        }
+        let popped: Vec<i32> = std::iter::from_fn(|| stack.pop()).collect();
+        assert_eq!(popped, vec![4, 3, 2, 1, 0]);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn concurrent_push_pop_loses_nothing() {
+        let stack = TreiberStack::new();
+        let popped = Mutex::new(Vec::new());
+        thread::scope(|s| {
+            for t in 0..4 {
+                let stack = &stack;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        stack.push(t * 1000 + i);
+                    }
+                });
+            }
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut mine = Vec::new();
+                    while mine.len() < 1000 {
+                        if let Some(x) = stack.pop() {
+                            mine.push(x);
+                        }
+                    }
+                    popped.lock().unwrap().extend(mine);
+                });
+            }
+        });
+        let mut popped = popped.into_inner().unwrap();
+        popped.sort();
+        assert_eq!(popped, (0..4000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn drops_remaining_items_once() {
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let drops = Arc::new(AtomicUsize::new(0));
+        let stack = TreiberStack::new();
+        for _ in 0..10 {
+            stack.push(Counted(Arc::clone(&drops)));
+        }
+        drop(stack.pop());
+        drop(stack);
+        assert_eq!(drops.load(Ordering::SeqCst), 10);
+    }
+}
@@ -2,6 +2,8 @@ use std::iter;
 
 use crossbeam_deque::{Injector, Stealer, Worker};
 
+pub mod collections;
+
 fn find_task<T>(local: &Worker<T>, global: &Injector<T>, stealers: &[Stealer<T>]) -> Option<T> {
     local.pop().or_else(|| {
         iter::repeat_with(|| {
@@ -0,0 +1,100 @@
+//! Spawning from worker threads with and without `PoolBuilder::task_cache`.
+//!
+//! Besides the criterion timings, the allocations made per spawned task are
+//! counted with a wrapping global allocator and printed first.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use criterion::{criterion_group, Criterion};
+use crossbeam::channel;
+use crossbeam_newfifo01::pool::{Factory, PoolBuilder};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const THREADS: usize = 4;
+const ROOTS: usize = 8;
+const CHILDREN: usize = 500;
+
+fn pool(task_cache: usize) -> Factory {
+    PoolBuilder::new()
+        .num_threads(THREADS)
+        .task_cache(task_cache)
+        .build()
+        .unwrap()
+}
+
+/// `ROOTS` tasks from outside, each spawning `CHILDREN` small tasks from its
+/// worker thread. Returns once all of them ran.
+fn fan_out(pool: &Factory) {
+    let remaining = Arc::new(AtomicUsize::new(ROOTS * CHILDREN));
+    let (done_tx, done_rx) = channel::bounded(1);
+    for _ in 0..ROOTS {
+        let (spawner, remaining, done_tx) =
+            (pool.spawner(), Arc::clone(&remaining), done_tx.clone());
+        pool.spawn(move || {
+            for _ in 0..CHILDREN {
+                let (remaining, done_tx) = (Arc::clone(&remaining), done_tx.clone());
+                spawner.spawn(move || {
+                    if remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+                        done_tx.send(()).unwrap();
+                    }
+                });
+            }
+        });
+    }
+    done_rx.recv().unwrap();
+}
+
+fn report_allocations() {
+    for (name, task_cache) in [("boxed", 0), ("task_cache(1024)", 1024)] {
+        let pool = pool(task_cache);
+        // Warm up the caches and the queues' buffers first.
+        (0..10).for_each(|_| fan_out(&pool));
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        (0..100).for_each(|_| fan_out(&pool));
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        println!(
+            "{name:>16}: {:.3} allocations per task",
+            allocations as f64 / (100 * ROOTS * (CHILDREN + 1)) as f64
+        );
+    }
+}
+
+fn spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn from workers");
+    for (name, task_cache) in [("boxed", 0), ("task_cache(1024)", 1024)] {
+        let pool = pool(task_cache);
+        group.bench_function(name, |b| b.iter(|| fan_out(&pool)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, spawn);
+
+fn main() {
+    report_allocations();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
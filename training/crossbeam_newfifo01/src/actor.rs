@@ -0,0 +1,339 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crossbeam::channel::{self, Receiver, SendError, Sender};
+
+use crate::pool::{Factory, Spawner};
+
+/// Messages an actor handles per turn before yielding its worker thread.
+const BATCH: usize = 32;
+
+/// State driven one message at a time, never from two threads at once.
+pub trait Actor: Send + 'static {
+    type Msg: Send + 'static;
+
+    fn handle(&mut self, msg: Self::Msg);
+}
+
+/// What the supervisor does when `handle` panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Restart {
+    /// Replace the actor with a fresh one and keep going.
+    #[default]
+    Always,
+    /// Restart at most this many times, then stop.
+    UpTo(usize),
+    /// Stop on the first panic.
+    Never,
+}
+
+struct Cell<A: Actor> {
+    mailbox: Receiver<A::Msg>,
+    actor: Mutex<A>,
+    make: Box<dyn Fn() -> A + Send + Sync>,
+    restart: Restart,
+    restarts: AtomicUsize,
+    // Set while a turn of this actor is queued or running.
+    scheduled: AtomicBool,
+    stopped: AtomicBool,
+    spawner: Spawner,
+}
+
+/// Handle to send messages to an actor, cheap to clone.
+///
+/// ```
+/// use crossbeam_newfifo01::{actor::Actor, pool::Factory};
+/// use crossbeam::channel;
+///
+/// struct Adder(u64, channel::Sender<u64>);
+///
+/// impl Actor for Adder {
+///     type Msg = u64;
+///     fn handle(&mut self, n: u64) {
+///         self.0 += n;
+///         self.1.send(self.0).unwrap();
+///     }
+/// }
+///
+/// let pool = Factory::new().build_threadpool(2);
+/// let (tx, rx) = channel::unbounded();
+/// let addr = pool.spawn_actor(move || Adder(0, tx.clone()));
+/// addr.send(2).unwrap();
+/// addr.send(3).unwrap();
+/// assert_eq!(rx.recv().unwrap(), 2);
+/// assert_eq!(rx.recv().unwrap(), 5);
+/// ```
+pub struct Addr<A: Actor> {
+    tx: Sender<A::Msg>,
+    cell: Arc<Cell<A>>,
+}
+
+impl<A: Actor> Clone for Addr<A> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            cell: Arc::clone(&self.cell),
+        }
+    }
+}
+
+impl<A: Actor> Addr<A> {
+    /// Queue `msg` in the mailbox. Fails once the supervisor stopped the actor.
+    pub fn send(&self, msg: A::Msg) -> Result<(), SendError<A::Msg>> {
+        if self.cell.stopped.load(Ordering::Acquire) {
+            return Err(SendError(msg));
+        }
+        self.tx.send(msg)?;
+        schedule(&self.cell);
+        Ok(())
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.cell.stopped.load(Ordering::Acquire)
+    }
+
+    /// How many times the actor was restarted after a panic.
+    pub fn restarts(&self) -> usize {
+        self.cell.restarts.load(Ordering::Relaxed)
+    }
+}
+
+impl Factory {
+    /// Start an actor built by `make`, restarted with a new `make()` whenever
+    /// a handler panics.
+    pub fn spawn_actor<A, F>(&self, make: F) -> Addr<A>
+    where
+        A: Actor,
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        self.spawn_actor_with(Restart::Always, make)
+    }
+
+    /// Like `spawn_actor` with another restart policy.
+    ///
+    /// The actor takes no thread of its own: whenever its mailbox has
+    /// messages, one turn of it is pushed on the pool's injector.
+    pub fn spawn_actor_with<A, F>(&self, restart: Restart, make: F) -> Addr<A>
+    where
+        A: Actor,
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        let (tx, rx) = channel::unbounded();
+        let cell = Arc::new(Cell {
+            mailbox: rx,
+            actor: Mutex::new(make()),
+            make: Box::new(make),
+            restart,
+            restarts: AtomicUsize::new(0),
+            scheduled: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            spawner: self.spawner(),
+        });
+        Addr { tx, cell }
+    }
+}
+
+fn schedule<A: Actor>(cell: &Arc<Cell<A>>) {
+    if !cell.scheduled.swap(true, Ordering::AcqRel) {
+        let cell_ = Arc::clone(cell);
+        cell.spawner.inject(Box::new(move || run(cell_)));
+    }
+}
+
+// One turn: handle up to `BATCH` messages, then requeue behind the other
+// actors if more are waiting.
+fn run<A: Actor>(cell: Arc<Cell<A>>) {
+    {
+        let mut actor = cell.actor.lock().unwrap_or_else(|e| e.into_inner());
+        for msg in cell.mailbox.try_iter().take(BATCH) {
+            if cell.stopped.load(Ordering::Acquire) {
+                break;
+            }
+            if panic::catch_unwind(AssertUnwindSafe(|| actor.handle(msg))).is_err() {
+                supervise(&cell, &mut actor);
+            }
+        }
+    }
+    if cell.stopped.load(Ordering::Acquire) {
+        // Drop what is left, senders already get an error.
+        cell.mailbox.try_iter().for_each(drop);
+        return;
+    }
+    cell.scheduled.store(false, Ordering::Release);
+    // A message sent while `scheduled` was still set didn't schedule a turn.
+    if !cell.mailbox.is_empty() {
+        schedule(&cell);
+    }
+}
+
+fn supervise<A: Actor>(cell: &Cell<A>, actor: &mut A) {
+    let restarts = cell.restarts.load(Ordering::Relaxed);
+    let restart = match cell.restart {
+        Restart::Always => true,
+        Restart::UpTo(max) => restarts < max,
+        Restart::Never => false,
+    };
+    if restart {
+        // A factory that panics can't be retried, stop like when out of restarts
+        if let Ok(fresh) = panic::catch_unwind(AssertUnwindSafe(|| (cell.make)())) {
+            cell.restarts.store(restarts + 1, Ordering::Relaxed);
+            *actor = fresh;
+            return;
+        }
+    }
+    cell.stopped.store(true, Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    struct Counter {
+        count: u64,
+        done: channel::Sender<u64>,
+    }
+
+    enum CounterMsg {
+        Add(u64),
+        Report,
+        Crash,
+    }
+
+    impl Actor for Counter {
+        type Msg = CounterMsg;
+
+        fn handle(&mut self, msg: CounterMsg) {
+            match msg {
+                CounterMsg::Add(n) => self.count += n,
+                CounterMsg::Report => self.done.send(self.count).unwrap(),
+                CounterMsg::Crash => panic!("crash"),
+            }
+        }
+    }
+
+    fn counter(pool: &Factory, restart: Restart) -> (Addr<Counter>, channel::Receiver<u64>) {
+        let (tx, rx) = channel::unbounded();
+        let addr = pool.spawn_actor_with(restart, move || Counter {
+            count: 0,
+            done: tx.clone(),
+        });
+        (addr, rx)
+    }
+
+    #[test]
+    fn thousands_of_actors_on_a_few_threads() {
+        let pool = Factory::new().build_threadpool(4);
+        let (tx, rx) = channel::unbounded();
+        let addrs: Vec<_> = (0..2000)
+            .map(|_| {
+                let tx = tx.clone();
+                pool.spawn_actor(move || Counter {
+                    count: 0,
+                    done: tx.clone(),
+                })
+            })
+            .collect();
+        for addr in &addrs {
+            for n in 1..=10 {
+                addr.send(CounterMsg::Add(n)).unwrap();
+            }
+            addr.send(CounterMsg::Report).unwrap();
+        }
+        let totals: Vec<u64> = rx.iter().take(2000).collect();
+        assert!(totals.iter().all(|t| *t == 55));
+    }
+
+    #[test]
+    fn messages_from_one_sender_arrive_in_order() {
+        type Entry = (usize, u32);
+        struct Log(Vec<Entry>, channel::Sender<Vec<Entry>>);
+        impl Actor for Log {
+            type Msg = Option<Entry>;
+            fn handle(&mut self, msg: Option<Entry>) {
+                match msg {
+                    Some(n) => self.0.push(n),
+                    None => self.1.send(self.0.clone()).unwrap(),
+                }
+            }
+        }
+        let pool = Factory::new().build_threadpool(3);
+        let (tx, rx) = channel::unbounded();
+        let addr = pool.spawn_actor(move || Log(Vec::new(), tx.clone()));
+        let senders: Vec<_> = (0..3)
+            .map(|sender| {
+                let addr = addr.clone();
+                thread::spawn(move || {
+                    for i in 0..100 {
+                        addr.send(Some((sender, i))).unwrap();
+                    }
+                })
+            })
+            .collect();
+        senders.into_iter().for_each(|s| s.join().unwrap());
+        addr.send(None).unwrap();
+        let log = rx.recv().unwrap();
+        assert_eq!(log.len(), 300);
+        // Messages from the 3 senders interleave, but never overtake each other.
+        for sender in 0..3 {
+            let mine: Vec<u32> = log.iter().filter(|e| e.0 == sender).map(|e| e.1).collect();
+            assert_eq!(mine, (0..100).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn panicking_handler_restarts_the_actor() {
+        let pool = Factory::new().build_threadpool(2);
+        let (addr, rx) = counter(&pool, Restart::Always);
+        addr.send(CounterMsg::Add(5)).unwrap();
+        addr.send(CounterMsg::Crash).unwrap();
+        addr.send(CounterMsg::Add(1)).unwrap();
+        addr.send(CounterMsg::Report).unwrap();
+        // State is lost with the crash, the mailbox isn't.
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), 1);
+        assert_eq!(addr.restarts(), 1);
+        assert!(!addr.is_stopped());
+    }
+
+    #[test]
+    fn actor_stops_once_out_of_restarts() {
+        let pool = Factory::new().build_threadpool(2);
+        let (addr, rx) = counter(&pool, Restart::UpTo(1));
+        addr.send(CounterMsg::Crash).unwrap();
+        addr.send(CounterMsg::Crash).unwrap();
+        addr.send(CounterMsg::Report).unwrap();
+        drop(pool);
+        assert!(addr.is_stopped());
+        assert_eq!(addr.restarts(), 1);
+        assert!(rx.try_recv().is_err());
+        assert!(addr.send(CounterMsg::Report).is_err());
+    }
+
+    #[test]
+    fn actor_stops_when_the_factory_panics_on_restart() {
+        let pool = Factory::new().build_threadpool(2);
+        let (tx, rx) = channel::unbounded();
+        let made = AtomicUsize::new(0);
+        let addr = pool.spawn_actor(move || {
+            if made.fetch_add(1, Ordering::Relaxed) > 0 {
+                panic!("can't make another counter");
+            }
+            Counter {
+                count: 0,
+                done: tx.clone(),
+            }
+        });
+        addr.send(CounterMsg::Crash).unwrap();
+        addr.send(CounterMsg::Report).unwrap();
+        drop(pool);
+        assert!(addr.is_stopped());
+        assert_eq!(addr.restarts(), 0);
+        assert!(rx.try_recv().is_err());
+        assert!(addr.send(CounterMsg::Report).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use std::{cmp::Reverse, env, path::PathBuf, process, thread, time::Instant};
+
+use crossbeam_newfifo01::mapreduce::{self, MapReduceOptions, WordCount, DEFAULT_CHUNK_SIZE};
+
+const USAGE: &str =
+    "usage: wordcount [--threads N] [--reducers N] [--chunk-size BYTES] [--top N] FILE...";
+
+struct Args {
+    options: MapReduceOptions,
+    top: Option<usize>,
+}
+
+fn parse_count(name: &str, value: Option<String>) -> Result<usize, String> {
+    let value = value.ok_or(format!("{name} needs a value"))?;
+    value
+        .parse()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or(format!("invalid value for {name}: {value}"))
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        options: MapReduceOptions {
+            inputs: Vec::new(),
+            threads: thread::available_parallelism().map_or(4, |n| n.get()),
+            reducers: 0,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        },
+        top: None,
+    };
+    let mut argv = env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--threads" | "-j" => args.options.threads = parse_count(&arg, argv.next())?,
+            "--reducers" | "-r" => args.options.reducers = parse_count(&arg, argv.next())?,
+            "--chunk-size" => args.options.chunk_size = parse_count(&arg, argv.next())?,
+            "--top" => args.top = Some(parse_count(&arg, argv.next())?),
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {arg}")),
+            _ => args.options.inputs.push(PathBuf::from(arg)),
+        }
+    }
+    if args.options.inputs.is_empty() {
+        return Err("no input files".to_string());
+    }
+    if args.options.reducers == 0 {
+        args.options.reducers = args.options.threads;
+    }
+    Ok(args)
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        process::exit(2);
+    });
+    let start = Instant::now();
+    let mut counts = mapreduce::run(WordCount, &args.options).unwrap_or_else(|e| {
+        eprintln!("wordcount: {e}");
+        process::exit(1);
+    });
+    let elapsed = start.elapsed();
+
+    if let Some(top) = args.top {
+        // Stable sort: ties stay in word order.
+        counts.sort_by_key(|(_, count)| Reverse(*count));
+        counts.truncate(top);
+    }
+    for (word, count) in &counts {
+        println!("{count:>8}  {word}");
+    }
+    // Timing on stderr, stdout is the same for any thread count.
+    eprintln!(
+        "counted {} files with {} threads and {} reducers in {elapsed:.3?}",
+        args.options.inputs.len(),
+        args.options.threads,
+        args.options.reducers
+    );
+}
@@ -0,0 +1,260 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    env,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::pool::{current_worker, Factory, PoolBuilder, Spawner};
+
+/// Overrides the thread count of the global pool.
+pub const NUM_THREADS_VAR: &str = "FACTORY_NUM_THREADS";
+
+/// How long `scope` sleeps when it has nothing to help with.
+const WAIT_SLICE: Duration = Duration::from_micros(100);
+
+static GLOBAL: OnceLock<Factory> = OnceLock::new();
+
+thread_local! {
+    // Pools installed on this thread by `install`, innermost last.
+    static INSTALLED: RefCell<Vec<Spawner>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The global pool, started on first use with one thread per available CPU
+/// or `FACTORY_NUM_THREADS` threads. It lives until the process exits.
+pub fn global_pool() -> &'static Factory {
+    GLOBAL.get_or_init(|| {
+        let mut builder = PoolBuilder::new().thread_name("factory-global");
+        if let Some(n) = threads_from_env(env::var(NUM_THREADS_VAR).ok().as_deref()) {
+            builder = builder.num_threads(n);
+        }
+        builder.build().expect("failed to start the global pool")
+    })
+}
+
+// Invalid values are ignored rather than failing the whole program.
+fn threads_from_env(value: Option<&str>) -> Option<usize> {
+    value?.trim().parse().ok().filter(|n| *n > 0)
+}
+
+// Pool the free functions use: the innermost `install`, else the pool of the
+// current worker thread, else the global one.
+fn current() -> Spawner {
+    INSTALLED
+        .with_borrow(|installed| installed.last().cloned())
+        .or_else(current_worker)
+        .unwrap_or_else(|| global_pool().spawner())
+}
+
+/// Run `f` on the current pool, see `install`.
+pub fn spawn<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    current().spawn(f)
+}
+
+/// `Factory::join` on the current pool, see `install`.
+pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    current().join(a, b)
+}
+
+/// Make `pool` the target of `spawn`, `join` and `scope` called on this
+/// thread while `f` runs. Tasks running on `pool`'s threads target it anyway.
+///
+/// ```
+/// use crossbeam_newfifo01::{global, pool::Factory};
+///
+/// let pool = Factory::new().build_threadpool(2);
+/// let (a, b) = global::install(&pool, || global::join(|| 1, || 2));
+/// assert_eq!(a + b, 3);
+/// ```
+pub fn install<R>(pool: &Factory, f: impl FnOnce() -> R) -> R {
+    struct Uninstall;
+    impl Drop for Uninstall {
+        fn drop(&mut self) {
+            INSTALLED.with_borrow_mut(|installed| installed.pop());
+        }
+    }
+    INSTALLED.with_borrow_mut(|installed| installed.push(pool.spawner()));
+    let _uninstall = Uninstall;
+    f()
+}
+
+/// Tasks of a `scope`, which may borrow anything that outlives it.
+pub struct Scope<'scope> {
+    spawner: Spawner,
+    pending: AtomicUsize,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    // Invariant in 'scope, like `std::thread::Scope`.
+    _marker: PhantomData<&'scope mut &'scope ()>,
+}
+
+struct ScopeRef<'scope>(*const Scope<'scope>);
+
+// SAFETY: `Scope` is `Sync`, and `scope` keeps it alive until every task that
+// holds a `ScopeRef` is done.
+unsafe impl Send for ScopeRef<'_> {}
+
+impl<'scope> Scope<'scope> {
+    /// Run `f` on the scope's pool. `scope` returns once it's done.
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce(&Scope<'scope>) + Send + 'scope,
+    {
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        let scope = ScopeRef(self);
+        let task = move || {
+            // Moves the whole `ScopeRef` in, not just its raw pointer field.
+            let scope = scope;
+            // SAFETY: see `ScopeRef`.
+            let scope = unsafe { &*scope.0 };
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| f(scope))) {
+                scope.panic.lock().unwrap().get_or_insert(payload);
+            }
+            scope.pending.fetch_sub(1, Ordering::AcqRel);
+        };
+        // SAFETY: `scope` waits for `pending` to drop to zero, so everything
+        // the task borrows outlives it.
+        unsafe { self.spawner.spawn_unchecked(Box::new(task)) };
+    }
+}
+
+/// Run `f` with a `Scope` on the current pool, and wait for every task it
+/// spawned before returning. The calling thread runs queued tasks while it
+/// waits. The first panic of `f` or of a task is propagated afterwards.
+///
+/// ```
+/// use crossbeam_newfifo01::global;
+///
+/// let mut v = vec![1, 2, 3, 4];
+/// global::scope(|s| {
+///     for x in v.iter_mut() {
+///         s.spawn(move |_| *x *= 10);
+///     }
+/// });
+/// assert_eq!(v, vec![10, 20, 30, 40]);
+/// ```
+pub fn scope<'scope, F, R>(f: F) -> R
+where
+    F: FnOnce(&Scope<'scope>) -> R,
+{
+    let scope = Scope {
+        spawner: current(),
+        pending: AtomicUsize::new(0),
+        panic: Mutex::new(None),
+        _marker: PhantomData,
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+    while scope.pending.load(Ordering::Acquire) > 0 {
+        if !scope.spawner.try_run_one() {
+            thread::sleep(WAIT_SLICE);
+        }
+    }
+    if let Some(payload) = scope.panic.into_inner().unwrap() {
+        panic::resume_unwind(payload);
+    }
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam::channel;
+
+    fn thread_name() -> String {
+        thread::current().name().unwrap_or("").to_string()
+    }
+
+    #[test]
+    fn env_override_parsing() {
+        assert_eq!(threads_from_env(Some("3")), Some(3));
+        assert_eq!(threads_from_env(Some(" 8\n")), Some(8));
+        assert_eq!(threads_from_env(Some("0")), None);
+        assert_eq!(threads_from_env(Some("many")), None);
+        assert_eq!(threads_from_env(None), None);
+    }
+
+    #[test]
+    fn free_functions_use_the_global_pool() {
+        let (tx, rx) = channel::unbounded();
+        spawn(move || tx.send(thread_name()).unwrap());
+        assert!(rx.recv().unwrap().starts_with("factory-global-"));
+        assert_eq!(join(|| 20, || 22), (20, 22));
+        assert!(global_pool().num_threads() > 0);
+    }
+
+    #[test]
+    fn install_redirects_to_another_pool() {
+        let pool = PoolBuilder::new()
+            .num_threads(2)
+            .thread_name("custom")
+            .build()
+            .unwrap();
+        let (tx, rx) = channel::unbounded();
+        install(&pool, || {
+            let tx = tx.clone();
+            // The nested spawn runs on a worker of `pool`, and stays there.
+            spawn(move || {
+                tx.send(thread_name()).unwrap();
+                spawn(move || tx.send(thread_name()).unwrap());
+            });
+        });
+        assert!(rx.recv().unwrap().starts_with("custom-"));
+        assert!(rx.recv().unwrap().starts_with("custom-"));
+        // Back to the global pool once `install` returned.
+        spawn(move || tx.send(thread_name()).unwrap());
+        assert!(rx.recv().unwrap().starts_with("factory-global-"));
+    }
+
+    #[test]
+    fn scope_borrows_and_waits() {
+        let mut chunks = vec![vec![1u64; 1000]; 8];
+        let total = AtomicUsize::new(0);
+        scope(|s| {
+            for chunk in chunks.iter_mut() {
+                let total = &total;
+                s.spawn(move |s| {
+                    chunk.iter_mut().for_each(|x| *x *= 2);
+                    // Tasks can spawn more tasks in the same scope.
+                    s.spawn(move |_| {
+                        total.fetch_add(1, Ordering::SeqCst);
+                    });
+                });
+            }
+        });
+        assert!(chunks.iter().flatten().all(|x| *x == 2));
+        assert_eq!(total.load(Ordering::SeqCst), 8);
+    }
+
+    #[test]
+    fn scope_propagates_a_task_panic_after_waiting() {
+        let done = AtomicUsize::new(0);
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            scope(|s| {
+                s.spawn(|_| panic!("task failed"));
+                for _ in 0..10 {
+                    s.spawn(|_| {
+                        thread::sleep(Duration::from_millis(1));
+                        done.fetch_add(1, Ordering::SeqCst);
+                    });
+                }
+            })
+        }));
+        assert_eq!(*res.unwrap_err().downcast::<&str>().unwrap(), "task failed");
+        assert_eq!(done.load(Ordering::SeqCst), 10);
+    }
+}
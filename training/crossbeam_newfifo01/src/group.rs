@@ -0,0 +1,274 @@
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::pool::Factory;
+
+/// How long `wait` sleeps when there is nothing to help with.
+const WAIT_SLICE: Duration = Duration::from_millis(1);
+
+/// Why a task of a group didn't produce a value.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TaskError<E> {
+    Failed(E),
+    Panicked(String),
+    /// Not run because another task failed first and the group is fail-fast.
+    Skipped,
+}
+
+impl<E: fmt::Display> fmt::Display for TaskError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::Failed(e) => write!(f, "{e}"),
+            TaskError::Panicked(msg) => write!(f, "panicked: {msg}"),
+            TaskError::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// Every failed task of a group, by submission index.
+#[derive(Debug)]
+pub struct GroupError<E> {
+    pub errors: Vec<(usize, TaskError<E>)>,
+}
+
+impl<E: fmt::Display> fmt::Display for GroupError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} task(s) failed", self.errors.len())?;
+        for (index, e) in &self.errors {
+            write!(f, "\n  task {index}: {e}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for GroupError<E> {}
+
+// One slot per spawned task, filled in when the task is done.
+type Slot<T, E> = Option<Result<T, TaskError<E>>>;
+
+struct State<T, E> {
+    results: Mutex<Vec<Slot<T, E>>>,
+    pending: Mutex<usize>,
+    done: Condvar,
+    failed: AtomicBool,
+}
+
+/// Batch of related tasks whose results are collected together.
+///
+/// ```
+/// use crossbeam_newfifo01::pool::Factory;
+///
+/// let pool = Factory::new().build_threadpool(2);
+/// let mut group = pool.group();
+/// for i in 0..4 {
+///     group.spawn(move || Ok::<_, String>(i * i));
+/// }
+/// assert_eq!(group.wait().unwrap(), vec![0, 1, 4, 9]);
+/// ```
+pub struct TaskGroup<'p, T, E> {
+    pool: &'p Factory,
+    state: Arc<State<T, E>>,
+    fail_fast: bool,
+}
+
+impl<'p, T, E> TaskGroup<'p, T, E>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    pub fn new(pool: &'p Factory) -> Self {
+        Self {
+            pool,
+            state: Arc::new(State {
+                results: Mutex::new(Vec::new()),
+                pending: Mutex::new(0),
+                done: Condvar::new(),
+                failed: AtomicBool::new(false),
+            }),
+            fail_fast: false,
+        }
+    }
+
+    /// After the first failure, skip the tasks that didn't start yet.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    pub fn spawn<F>(&mut self, f: F)
+    where
+        F: FnOnce() -> Result<T, E> + Send + 'static,
+    {
+        let index = {
+            let mut results = self.state.results.lock().unwrap();
+            results.push(None);
+            results.len() - 1
+        };
+        *self.state.pending.lock().unwrap() += 1;
+
+        let state = Arc::clone(&self.state);
+        let fail_fast = self.fail_fast;
+        self.pool.spawn(move || {
+            let result = if fail_fast && state.failed.load(Ordering::Acquire) {
+                Err(TaskError::Skipped)
+            } else {
+                match panic::catch_unwind(AssertUnwindSafe(f)) {
+                    Ok(Ok(value)) => Ok(value),
+                    Ok(Err(e)) => Err(TaskError::Failed(e)),
+                    Err(payload) => Err(TaskError::Panicked(panic_message(payload.as_ref()))),
+                }
+            };
+            if result.is_err() {
+                state.failed.store(true, Ordering::Release);
+            }
+            state.results.lock().unwrap()[index] = Some(result);
+
+            let mut pending = state.pending.lock().unwrap();
+            *pending -= 1;
+            if *pending == 0 {
+                state.done.notify_all();
+            }
+        });
+    }
+
+    /// Wait for every task of the group, running queued pool work on this
+    /// thread meanwhile. Values come back in submission order.
+    pub fn wait(self) -> Result<Vec<T>, GroupError<E>> {
+        loop {
+            if *self.state.pending.lock().unwrap() == 0 {
+                break;
+            }
+            if self.pool.try_run_one() {
+                continue;
+            }
+            let pending = self.state.pending.lock().unwrap();
+            if *pending == 0 {
+                break;
+            }
+            let _ = self.state.done.wait_timeout(pending, WAIT_SLICE).unwrap();
+        }
+
+        let results = std::mem::take(&mut *self.state.results.lock().unwrap());
+        let mut values = Vec::with_capacity(results.len());
+        let mut errors = Vec::new();
+        for (index, result) in results.into_iter().enumerate() {
+            match result.expect("task finished without a result") {
+                Ok(value) => values.push(value),
+                Err(e) => errors.push((index, e)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(values)
+        } else {
+            Err(GroupError { errors })
+        }
+    }
+}
+
+impl Factory {
+    /// Start a new `TaskGroup` on this pool.
+    pub fn group<T, E>(&self) -> TaskGroup<'_, T, E>
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        TaskGroup::new(self)
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn results_in_submission_order() {
+        let pool = Factory::new().build_threadpool(4);
+        let mut group = pool.group::<u64, ()>();
+        for i in 0..50u64 {
+            group.spawn(move || {
+                thread::sleep(Duration::from_micros((50 - i) * 20));
+                Ok(i)
+            });
+        }
+        assert_eq!(group.wait().unwrap(), (0..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn wait_runs_the_tasks_without_worker_threads() {
+        let pool = Factory::new();
+        let mut group = pool.group::<i32, ()>();
+        group.spawn(|| Ok(1));
+        group.spawn(|| Ok(2));
+        assert_eq!(group.wait().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn errors_are_aggregated() {
+        let pool = Factory::new().build_threadpool(2);
+        let mut group = pool.group::<i32, String>();
+        group.spawn(|| Ok(1));
+        group.spawn(|| Err("bad".to_string()));
+        group.spawn(|| panic!("worse"));
+        group.spawn(|| Ok(4));
+        let err = group.wait().unwrap_err();
+        assert_eq!(
+            err.errors,
+            vec![
+                (1, TaskError::Failed("bad".to_string())),
+                (2, TaskError::Panicked("worse".to_string())),
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            "2 task(s) failed\n  task 1: bad\n  task 2: panicked: worse"
+        );
+    }
+
+    #[test]
+    fn fail_fast_skips_tasks_not_started() {
+        // No worker threads, so `wait` runs the tasks in order on this thread.
+        let pool = Factory::new();
+        let mut group = pool.group::<i32, &str>().fail_fast(true);
+        group.spawn(|| Ok(0));
+        group.spawn(|| Err("stop"));
+        group.spawn(|| Ok(2));
+        let err = group.wait().unwrap_err();
+        assert_eq!(
+            err.errors,
+            vec![(1, TaskError::Failed("stop")), (2, TaskError::Skipped)]
+        );
+    }
+
+    #[test]
+    fn nested_group_inside_a_task() {
+        let pool = Arc::new(Factory::new().build_threadpool(2));
+        let inner_pool = Arc::clone(&pool);
+        let mut outer = pool.group::<i32, ()>();
+        outer.spawn(move || {
+            let mut inner = inner_pool.group::<i32, ()>();
+            for i in 0..10 {
+                inner.spawn(move || Ok(i));
+            }
+            Ok(inner.wait().unwrap().into_iter().sum())
+        });
+        assert_eq!(outer.wait().unwrap(), vec![45]);
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{BuildHasher, Hash, RandomState},
+    sync::{Arc, Mutex},
+};
+
+use crate::pool::{run_task, Factory, Spawner, Task};
+
+/// Pending tasks of every key that has one scheduled or running.
+///
+/// Keys are stored by hash: two keys sharing a hash are serialised together,
+/// which is slower but keeps both guarantees.
+#[derive(Default)]
+pub(crate) struct KeyedQueues {
+    hasher: RandomState,
+    queues: Mutex<HashMap<u64, VecDeque<Task>>>,
+}
+
+impl Factory {
+    /// Run `f` after every task spawned earlier with the same `key`, and never
+    /// at the same time as one of them. Different keys still run in parallel.
+    ///
+    /// Each key's queue is a single task on the injector at any time, so
+    /// idle threads steal freely across keys.
+    pub fn spawn_keyed<K, F>(&self, key: &K, f: F)
+    where
+        K: Hash + ?Sized,
+        F: FnOnce() + Send + 'static,
+    {
+        let keyed = self.keyed();
+        let hash = keyed.hasher.hash_one(key);
+        let task: Task = Box::new(f);
+        {
+            let mut queues = keyed.queues.lock().unwrap();
+            if let Some(queue) = queues.get_mut(&hash) {
+                // The key is already scheduled, its runner will get to it.
+                queue.push_back(task);
+                return;
+            }
+            queues.insert(hash, VecDeque::new());
+        }
+        schedule(self.spawner(), Arc::clone(keyed), hash, task);
+    }
+}
+
+// Run one task of the key, then put the key back behind the other work.
+fn schedule(spawner: Spawner, keyed: Arc<KeyedQueues>, hash: u64, task: Task) {
+    let next_spawner = spawner.clone();
+    spawner.inject(Box::new(move || {
+        // `run_task` swallows panics, a bad task must not stall its key.
+        run_task(task);
+        let next = {
+            let mut queues = keyed.queues.lock().unwrap();
+            let queue = queues.get_mut(&hash).expect("running key without a queue");
+            let next = queue.pop_front();
+            if next.is_none() {
+                queues.remove(&hash);
+            }
+            next
+        };
+        if let Some(next) = next {
+            schedule(next_spawner, keyed, hash, next);
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam::channel;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn fifo_and_exclusive_within_a_key() {
+        let pool = Factory::new().build_threadpool(4);
+        let running: Arc<Vec<AtomicUsize>> =
+            Arc::new((0..8).map(|_| AtomicUsize::new(0)).collect());
+        let seen = Arc::new(Mutex::new(vec![Vec::new(); 8]));
+        for i in 0..400 {
+            let key = i % 8;
+            let running = Arc::clone(&running);
+            let seen = Arc::clone(&seen);
+            pool.spawn_keyed(&key, move || {
+                assert_eq!(running[key].fetch_add(1, Ordering::SeqCst), 0);
+                thread::sleep(Duration::from_micros(50));
+                seen.lock().unwrap()[key].push(i);
+                running[key].fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        drop(pool);
+        for (key, order) in seen.lock().unwrap().iter().enumerate() {
+            let expected: Vec<usize> = (0..400).filter(|i| i % 8 == key).collect();
+            assert_eq!(*order, expected);
+        }
+    }
+
+    #[test]
+    fn different_keys_run_in_parallel() {
+        let pool = Factory::new().build_threadpool(2);
+        let (a_tx, a_rx) = channel::bounded(1);
+        let (b_tx, b_rx) = channel::bounded(1);
+        let (done_tx, done_rx) = channel::unbounded();
+        let done_a = done_tx.clone();
+        // Each side waits for the other one, serialising them would time out.
+        pool.spawn_keyed("customer-a", move || {
+            a_tx.send(()).unwrap();
+            done_a
+                .send(b_rx.recv_timeout(Duration::from_secs(5)).is_ok())
+                .unwrap();
+        });
+        pool.spawn_keyed("customer-b", move || {
+            b_tx.send(()).unwrap();
+            done_tx
+                .send(a_rx.recv_timeout(Duration::from_secs(5)).is_ok())
+                .unwrap();
+        });
+        assert!(done_rx.recv().unwrap());
+        assert!(done_rx.recv().unwrap());
+    }
+
+    #[test]
+    fn panicking_task_does_not_stall_its_key() {
+        let pool = Factory::new().build_threadpool(2);
+        let counter = Arc::new(AtomicUsize::new(0));
+        pool.spawn_keyed(&1u32, || panic!("bad job"));
+        let c = Arc::clone(&counter);
+        pool.spawn_keyed(&1u32, move || {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        drop(pool);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn finished_keys_are_forgotten() {
+        let pool = Factory::new().build_threadpool(2);
+        for i in 0..50u32 {
+            pool.spawn_keyed(&i, || {});
+        }
+        let keyed = Arc::clone(pool.keyed());
+        drop(pool);
+        assert!(keyed.queues.lock().unwrap().is_empty());
+    }
+}
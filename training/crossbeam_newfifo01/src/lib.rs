@@ -0,0 +1,12 @@
+pub mod actor;
+pub mod global;
+pub mod group;
+pub mod keyed;
+pub mod mapreduce;
+pub mod pipeline;
+pub mod pool;
+pub mod scan;
+pub mod sharded;
+pub mod sim;
+mod slab;
+pub mod sort;
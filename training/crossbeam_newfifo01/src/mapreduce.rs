@@ -0,0 +1,272 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    convert::Infallible,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use crate::pool::Factory;
+
+/// Default size of a map task's input, in bytes.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A map and a reduce function over text.
+pub trait Job: Send + Sync + 'static {
+    type Key: Hash + Ord + Clone + Send + 'static;
+    type Value: Send + 'static;
+    type Output: Send + 'static;
+
+    fn map(&self, chunk: &str, emit: &mut dyn FnMut(Self::Key, Self::Value));
+
+    /// Called once per key, with the values of every map task.
+    fn reduce(&self, key: &Self::Key, values: Vec<Self::Value>) -> Self::Output;
+}
+
+#[derive(Debug, Clone)]
+pub struct MapReduceOptions {
+    pub inputs: Vec<PathBuf>,
+    pub threads: usize,
+    pub reducers: usize,
+    /// Chunks end on a line break, so they may run a bit longer than this.
+    pub chunk_size: usize,
+}
+
+// Intermediate pairs: one map per reducer out of a map task, or one map per
+// map task into a reducer.
+type Partitions<K, V> = Vec<HashMap<K, Vec<V>>>;
+
+/// Run `job` over the input files, results sorted by key.
+///
+/// Every chunk is a map task on the pool's injector, idle workers steal them.
+/// Each map task partitions its output by key hash; once all of them are done,
+/// one reduce task per partition runs. Sorting by key at the end makes the
+/// output the same whatever the thread and reducer count.
+pub fn run<J: Job>(job: J, options: &MapReduceOptions) -> io::Result<Vec<(J::Key, J::Output)>> {
+    let job = Arc::new(job);
+    let reducers = options.reducers.max(1);
+    let pool = Factory::new().build_threadpool(options.threads.max(1));
+
+    let mut maps = pool.group::<Partitions<J::Key, J::Value>, Infallible>();
+    for path in &options.inputs {
+        let text = fs::read_to_string(path)?;
+        for chunk in split_chunks(&text, options.chunk_size.max(1)) {
+            let (job, chunk) = (Arc::clone(&job), chunk.to_string());
+            maps.spawn(move || Ok(map_chunk(&*job, &chunk, reducers)));
+        }
+    }
+    let mapped = maps
+        .wait()
+        .map_err(|e| io::Error::other(format!("map phase: {e}")))?;
+
+    // Shuffle: reducer `r` gets partition `r` of every map task.
+    let mut shuffled: Vec<Partitions<J::Key, J::Value>> =
+        (0..reducers).map(|_| Vec::new()).collect();
+    for partitions in mapped {
+        for (r, partition) in partitions.into_iter().enumerate() {
+            shuffled[r].push(partition);
+        }
+    }
+
+    let mut reduces = pool.group::<Vec<(J::Key, J::Output)>, Infallible>();
+    for partitions in shuffled {
+        let job = Arc::clone(&job);
+        reduces.spawn(move || Ok(reduce_partition(&*job, partitions)));
+    }
+    let reduced = reduces
+        .wait()
+        .map_err(|e| io::Error::other(format!("reduce phase: {e}")))?;
+
+    let mut output: Vec<(J::Key, J::Output)> = reduced.into_iter().flatten().collect();
+    output.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(output)
+}
+
+fn map_chunk<J: Job>(job: &J, chunk: &str, reducers: usize) -> Partitions<J::Key, J::Value> {
+    let mut partitions: Partitions<J::Key, J::Value> =
+        (0..reducers).map(|_| HashMap::new()).collect();
+    job.map(chunk, &mut |key, value| {
+        partitions[partition_of(&key, reducers)]
+            .entry(key)
+            .or_default()
+            .push(value);
+    });
+    partitions
+}
+
+fn reduce_partition<J: Job>(
+    job: &J,
+    partitions: Partitions<J::Key, J::Value>,
+) -> Vec<(J::Key, J::Output)> {
+    let mut grouped: HashMap<J::Key, Vec<J::Value>> = HashMap::new();
+    for partition in partitions {
+        for (key, values) in partition {
+            grouped.entry(key).or_default().extend(values);
+        }
+    }
+    grouped
+        .into_iter()
+        .map(|(key, values)| {
+            let output = job.reduce(&key, values);
+            (key, output)
+        })
+        .collect()
+}
+
+// `DefaultHasher::new` uses fixed keys, so a key lands in the same partition
+// on every run.
+fn partition_of<K: Hash>(key: &K, reducers: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % reducers as u64) as usize
+}
+
+/// Split `text` in pieces of about `size` bytes, cut after a line break (or
+/// any whitespace for very long lines) so no word is split in two.
+pub fn split_chunks(text: &str, size: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > size {
+        let mut end = size;
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        let cut = rest[end..]
+            .find('\n')
+            .map(|i| end + i + 1)
+            .or_else(|| {
+                rest[end..]
+                    .char_indices()
+                    .find(|(_, c)| c.is_whitespace())
+                    .map(|(i, c)| end + i + c.len_utf8())
+            })
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(cut);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// Reference job: how many times each word appears, case-insensitive.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WordCount;
+
+impl Job for WordCount {
+    type Key = String;
+    type Value = u64;
+    type Output = u64;
+
+    fn map(&self, chunk: &str, emit: &mut dyn FnMut(String, u64)) {
+        for word in chunk
+            .split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .map(|w| w.trim_matches('\''))
+            .filter(|w| !w.is_empty())
+        {
+            emit(word.to_lowercase(), 1);
+        }
+    }
+
+    fn reduce(&self, _word: &String, counts: Vec<u64>) -> u64 {
+        counts.into_iter().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> Vec<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("newfifo01-mr-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.txt");
+        let b = dir.join("b.txt");
+        fs::write(
+            &a,
+            "the quick brown fox\njumps over the lazy dog\n".repeat(50),
+        )
+        .unwrap();
+        fs::write(&b, "The dog's bark, the fox's den.\nIt's the end").unwrap();
+        vec![a, b]
+    }
+
+    #[test]
+    fn chunks_cover_the_text_without_splitting_words() {
+        let text = "one two three\nfour five\nsix seven eight nine\nten";
+        for size in [1, 4, 10, 100] {
+            let chunks = split_chunks(text, size);
+            assert_eq!(chunks.concat(), text);
+            for pair in chunks.windows(2) {
+                let last = pair[0].chars().last().unwrap();
+                assert!(last.is_whitespace(), "{chunks:?}");
+            }
+        }
+        assert_eq!(split_chunks("", 8), Vec::<&str>::new());
+        assert_eq!(split_chunks("héllo wörld", 2), vec!["héllo ", "wörld"]);
+    }
+
+    #[test]
+    fn word_count() {
+        let inputs = fixture("count");
+        let counts = run(
+            WordCount,
+            &MapReduceOptions {
+                inputs: inputs.clone(),
+                threads: 4,
+                reducers: 3,
+                chunk_size: 16,
+            },
+        )
+        .unwrap();
+        let get = |w: &str| counts.iter().find(|(k, _)| k == w).map(|(_, c)| *c);
+        assert_eq!(get("the"), Some(103));
+        assert_eq!(get("fox"), Some(50));
+        assert_eq!(get("dog's"), Some(1));
+        assert_eq!(get("it's"), Some(1));
+        assert_eq!(get("cat"), None);
+        assert!(counts.windows(2).all(|w| w[0].0 < w[1].0));
+        fs::remove_dir_all(inputs[0].parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn output_does_not_depend_on_threads_or_reducers() {
+        let inputs = fixture("determinism");
+        let results: Vec<_> = [(1, 1, 1 << 20), (2, 5, 7), (8, 16, 64)]
+            .into_iter()
+            .map(|(threads, reducers, chunk_size)| {
+                run(
+                    WordCount,
+                    &MapReduceOptions {
+                        inputs: inputs.clone(),
+                        threads,
+                        reducers,
+                        chunk_size,
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[0], results[2]);
+        fs::remove_dir_all(inputs[0].parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_input_is_an_error() {
+        let res = run(
+            WordCount,
+            &MapReduceOptions {
+                inputs: vec![PathBuf::from("/definitely/not/here.txt")],
+                threads: 2,
+                reducers: 2,
+                chunk_size: DEFAULT_CHUNK_SIZE,
+            },
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    collections::BTreeMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::{self, Receiver, Sender};
+
+use crate::pool::Factory;
+
+/// Default capacity of the bounded channels linking two stages.
+const DEFAULT_CAPACITY: usize = 64;
+
+// Items travel with their input position so the output can be put back in
+// order. `None` marks an item that was dropped because a stage panicked on it.
+type Item<T> = (u64, Option<T>);
+
+type Connect<In, Out> = Box<
+    dyn FnOnce(Receiver<Item<In>>, usize, &mut Vec<JoinHandle<()>>) -> Receiver<Item<Out>> + Send,
+>;
+
+#[derive(Default)]
+struct StageMetrics {
+    items_in: AtomicU64,
+    items_out: AtomicU64,
+    panicked: AtomicU64,
+    busy_nanos: AtomicU64,
+}
+
+/// Snapshot of the counters of one stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageStats {
+    pub items_in: u64,
+    pub items_out: u64,
+    pub panicked: u64,
+    /// Time spent inside the stage function, summed over all workers.
+    pub busy: Duration,
+}
+
+impl From<&StageMetrics> for StageStats {
+    fn from(m: &StageMetrics) -> Self {
+        Self {
+            items_in: m.items_in.load(Ordering::Relaxed),
+            items_out: m.items_out.load(Ordering::Relaxed),
+            panicked: m.panicked.load(Ordering::Relaxed),
+            busy: Duration::from_nanos(m.busy_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Chain of stages, each one backed by its own work-stealing `Factory`
+/// and linked to the next one by a bounded channel.
+///
+/// ```
+/// use crossbeam_newfifo01::pipeline::Pipeline;
+///
+/// let out: Vec<i32> = Pipeline::new()
+///     .ordered(true)
+///     .stage(4, |x: i32| x * 2)
+///     .stage(2, |x: i32| x + 1)
+///     .run(0..5)
+///     .collect();
+/// assert_eq!(out, vec![1, 3, 5, 7, 9]);
+/// ```
+pub struct Pipeline<In, Out> {
+    connect: Connect<In, Out>,
+    metrics: Vec<Arc<StageMetrics>>,
+    capacity: usize,
+    ordered: bool,
+}
+
+impl<T: Send + 'static> Pipeline<T, T> {
+    pub fn new() -> Self {
+        Self {
+            connect: Box::new(|rx, _, _| rx),
+            metrics: Vec::new(),
+            capacity: DEFAULT_CAPACITY,
+            ordered: false,
+        }
+    }
+}
+
+impl<T: Send + 'static> Default for Pipeline<T, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<In: Send + 'static, Out: Send + 'static> Pipeline<In, Out> {
+    /// Capacity of every channel between stages, also the maximum number of
+    /// items in flight inside a single stage.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Yield the output in input order instead of completion order.
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// Append a stage running `f` on `n_workers` threads.
+    pub fn stage<U, F>(self, n_workers: usize, f: F) -> Pipeline<In, U>
+    where
+        U: Send + 'static,
+        F: Fn(Out) -> U + Send + Sync + 'static,
+    {
+        let metrics = Arc::new(StageMetrics::default());
+        let stage_metrics = Arc::clone(&metrics);
+        let prev = self.connect;
+        let mut all_metrics = self.metrics;
+        all_metrics.push(metrics);
+
+        Pipeline {
+            connect: Box::new(move |rx, capacity, handles| {
+                let rx = prev(rx, capacity, handles);
+                let (tx, out_rx) = channel::bounded(capacity);
+                handles.push(spawn_stage(n_workers, capacity, f, rx, tx, stage_metrics));
+                out_rx
+            }),
+            metrics: all_metrics,
+            capacity: self.capacity,
+            ordered: self.ordered,
+        }
+    }
+
+    /// Feed `input` through every stage and return an iterator over the results.
+    pub fn run<I>(self, input: I) -> PipelineOutput<Out>
+    where
+        I: IntoIterator<Item = In>,
+        I::IntoIter: Send + 'static,
+    {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = channel::bounded(self.capacity);
+        let input = input.into_iter();
+        let feeder_cancelled = Arc::clone(&cancelled);
+        let mut handles = vec![thread::spawn(move || {
+            for (seq, item) in (0u64..).zip(input) {
+                if feeder_cancelled.load(Ordering::Relaxed) || tx.send((seq, Some(item))).is_err() {
+                    break;
+                }
+            }
+        })];
+        let rx = (self.connect)(rx, self.capacity, &mut handles);
+
+        PipelineOutput {
+            rx,
+            ordered: self.ordered,
+            next_seq: 0,
+            pending: BTreeMap::new(),
+            metrics: self.metrics,
+            handles,
+            cancelled,
+        }
+    }
+}
+
+fn spawn_stage<T, U, F>(
+    n_workers: usize,
+    capacity: usize,
+    f: F,
+    input: Receiver<Item<T>>,
+    output: Sender<Item<U>>,
+    metrics: Arc<StageMetrics>,
+) -> JoinHandle<()>
+where
+    T: Send + 'static,
+    U: Send + 'static,
+    F: Fn(T) -> U + Send + Sync + 'static,
+{
+    thread::spawn(move || {
+        let pool = Factory::new().build_threadpool(n_workers.max(1));
+        let f = Arc::new(f);
+        // Every in-flight item holds a permit, so a slow stage can't pile up
+        // more than `capacity` tasks on its injector.
+        let (permits, released) = channel::bounded::<()>(capacity);
+
+        for (seq, item) in input {
+            if permits.send(()).is_err() {
+                break;
+            }
+            let f = Arc::clone(&f);
+            let output = output.clone();
+            let metrics = Arc::clone(&metrics);
+            let released = released.clone();
+            pool.spawn(move || {
+                let out = item.and_then(|item| {
+                    metrics.items_in.fetch_add(1, Ordering::Relaxed);
+                    let start = Instant::now();
+                    let res = panic::catch_unwind(AssertUnwindSafe(|| f(item)));
+                    metrics
+                        .busy_nanos
+                        .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+                    match res {
+                        Ok(out) => {
+                            metrics.items_out.fetch_add(1, Ordering::Relaxed);
+                            Some(out)
+                        }
+                        Err(_) => {
+                            metrics.panicked.fetch_add(1, Ordering::Relaxed);
+                            None
+                        }
+                    }
+                });
+                let _ = output.send((seq, out));
+                let _ = released.recv();
+            });
+        }
+        // Dropping the pool runs the remaining tasks, so the next stage only
+        // sees end-of-stream once every item of this one went through.
+        drop(pool);
+    })
+}
+
+/// Results of a running pipeline.
+pub struct PipelineOutput<T> {
+    rx: Receiver<Item<T>>,
+    ordered: bool,
+    next_seq: u64,
+    pending: BTreeMap<u64, Option<T>>,
+    metrics: Vec<Arc<StageMetrics>>,
+    handles: Vec<JoinHandle<()>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<T> PipelineOutput<T> {
+    /// Counters of every stage, in the order the stages were added.
+    pub fn metrics(&self) -> Vec<StageStats> {
+        self.metrics
+            .iter()
+            .map(|m| StageStats::from(m.as_ref()))
+            .collect()
+    }
+
+    fn join(&mut self) {
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl<T> Iterator for PipelineOutput<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if self.ordered {
+                if let Some(item) = self.pending.remove(&self.next_seq) {
+                    self.next_seq += 1;
+                    match item {
+                        Some(item) => return Some(item),
+                        None => continue,
+                    }
+                }
+            }
+            match self.rx.recv() {
+                Ok((seq, item)) if self.ordered => {
+                    self.pending.insert(seq, item);
+                }
+                Ok((_, Some(item))) => return Some(item),
+                Ok((_, None)) => {}
+                Err(_) => {
+                    self.join();
+                    // Every sequence number made it through, unless the feeder
+                    // was cut short; flush whatever is left in order, past the
+                    // items dropped on a panic.
+                    while let Some((_, item)) = self.pending.pop_first() {
+                        if item.is_some() {
+                            return item;
+                        }
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for PipelineOutput<T> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        // Drain so that no stage stays blocked on a full channel.
+        while self.rx.recv().is_ok() {}
+        self.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered_output_follows_input_order() {
+        let out: Vec<u64> = Pipeline::new()
+            .ordered(true)
+            .capacity(4)
+            .stage(4, |x: u64| {
+                thread::sleep(Duration::from_micros((x % 7) * 100));
+                x * 2
+            })
+            .stage(3, |x: u64| x + 1)
+            .run(0..200)
+            .collect();
+        assert_eq!(out, (0..200).map(|x| x * 2 + 1).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unordered_output_contains_every_item() {
+        let mut out: Vec<String> = Pipeline::new()
+            .stage(3, |x: i32| x * 10)
+            .stage(2, |x: i32| x.to_string())
+            .run(0..100)
+            .collect();
+        out.sort_by_key(|s| s.parse::<i32>().unwrap());
+        assert_eq!(
+            out,
+            (0..100).map(|x| (x * 10).to_string()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn empty_input_ends_the_stream() {
+        let mut output = Pipeline::new().stage(2, |x: i32| x).run(Vec::new());
+        assert_eq!(output.next(), None);
+        assert_eq!(output.metrics()[0].items_in, 0);
+    }
+
+    #[test]
+    fn metrics_and_panicking_items() {
+        let mut output = Pipeline::new()
+            .ordered(true)
+            .stage(2, |x: i32| {
+                if x == 3 {
+                    panic!("bad item");
+                }
+                x
+            })
+            .stage(2, |x: i32| x * x)
+            .run(0..6);
+        let out: Vec<i32> = output.by_ref().collect();
+        assert_eq!(out, vec![0, 1, 4, 16, 25]);
+
+        let stats = output.metrics();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].items_in, 6);
+        assert_eq!(stats[0].items_out, 5);
+        assert_eq!(stats[0].panicked, 1);
+        assert_eq!(stats[1].items_in, 5);
+        assert_eq!(stats[1].items_out, 5);
+    }
+
+    #[test]
+    fn flush_after_a_gap_skips_panicked_items() {
+        let (tx, rx) = channel::bounded(1);
+        drop(tx);
+        let mut output = PipelineOutput {
+            rx,
+            ordered: true,
+            next_seq: 0,
+            pending: BTreeMap::from([(2, None), (3, Some(3)), (4, None), (5, None), (6, Some(6))]),
+            metrics: Vec::new(),
+            handles: Vec::new(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+        assert_eq!(output.by_ref().collect::<Vec<i32>>(), vec![3, 6]);
+    }
+
+    #[test]
+    fn dropping_the_output_early_stops_the_pipeline() {
+        let mut output = Pipeline::new().capacity(2).stage(2, |x: u64| x).run(0..);
+        assert!(output.next().is_some());
+        drop(output);
+    }
+}
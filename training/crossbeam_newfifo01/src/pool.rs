@@ -0,0 +1,872 @@
+use std::{
+    cell::{RefCell, UnsafeCell},
+    io, iter, mem,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, OnceLock,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossbeam::deque::{Injector, Stealer, Worker};
+
+use crate::{
+    keyed::KeyedQueues,
+    slab::{self, PooledTask},
+};
+
+/// A unit of work scheduled on the pool.
+pub type Task = Box<dyn FnOnce() + Send + 'static>;
+
+/// Hook called with the index of a worker thread.
+pub type ThreadHook = Arc<dyn Fn(usize) + Send + Sync + 'static>;
+
+/// Default time an idle thread sleeps before looking for work again.
+const IDLE_WAIT: Duration = Duration::from_millis(10);
+
+// What the queues hold: a boxed task, or one in a recycled node of the
+// spawning worker's task cache.
+enum Job {
+    Boxed(Task),
+    Pooled(PooledTask),
+}
+
+impl Job {
+    fn new<F>(f: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        slab::alloc(f).map_or_else(|f| Job::Boxed(Box::new(f)), Job::Pooled)
+    }
+}
+
+struct Shared {
+    injector: Injector<Job>,               // common global queue
+    stealers: OnceLock<Vec<Stealer<Job>>>, // stealers for every thread local queue, set on build
+    shutdown: AtomicBool,
+    sleep_lock: Mutex<()>,
+    sleep_cvar: Condvar,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            injector: Injector::new(),
+            stealers: OnceLock::new(),
+            shutdown: AtomicBool::new(false),
+            sleep_lock: Mutex::new(()),
+            sleep_cvar: Condvar::new(),
+        }
+    }
+
+    fn stealers(&self) -> &[Stealer<Job>] {
+        self.stealers.get().map_or(&[], Vec::as_slice)
+    }
+
+    fn notify_one(&self) {
+        let _guard = self.sleep_lock.lock().unwrap();
+        self.sleep_cvar.notify_one();
+    }
+
+    fn notify_all(&self) {
+        let _guard = self.sleep_lock.lock().unwrap();
+        self.sleep_cvar.notify_all();
+    }
+
+    // See `Factory::join`.
+    fn join<A, B, RA, RB>(self: &Arc<Self>, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        let job = JoinJob {
+            func: UnsafeCell::new(Some(b)),
+            result: UnsafeCell::new(None),
+            done: AtomicBool::new(false),
+        };
+        // Whoever flips `claimed` first runs `b`. The queued ticket may outlive
+        // this frame, so it only touches `job` after winning the claim.
+        let claimed = Arc::new(AtomicBool::new(false));
+        let ticket = JobRef(&job);
+        let ticket_claimed = Arc::clone(&claimed);
+        let task: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+            if !ticket_claimed.swap(true, Ordering::AcqRel) {
+                // SAFETY: the claim was won, so `join` is still waiting on `done`.
+                unsafe { ticket.run() }
+            }
+        });
+        // SAFETY: the task only dereferences `job` while `join` waits for it,
+        // see above; `join` never leaves early, even if `a` panics.
+        let task: Task = unsafe { mem::transmute(task) };
+        push(self, Job::Boxed(task));
+
+        let ra = panic::catch_unwind(AssertUnwindSafe(a));
+        if !claimed.swap(true, Ordering::AcqRel) {
+            job.run();
+        } else {
+            while !job.done.load(Ordering::Acquire) {
+                thread::yield_now();
+            }
+        }
+        let rb = job
+            .result
+            .into_inner()
+            .expect("join job finished without a result");
+        match (ra, rb) {
+            (Ok(ra), Ok(rb)) => (ra, rb),
+            (Err(payload), _) | (_, Err(payload)) => panic::resume_unwind(payload),
+        }
+    }
+
+    fn try_run_one(self: &Arc<Self>) -> bool {
+        let task = CURRENT.with_borrow(|current| match current {
+            Some(data) if Arc::ptr_eq(&data.shared, self) => {
+                find_task(&data.task_q, &self.injector, self.stealers())
+            }
+            _ => iter::repeat_with(|| {
+                self.injector
+                    .steal()
+                    .or_else(|| self.stealers().iter().map(|s| s.steal()).collect())
+            })
+            .find(|s| !s.is_retry())
+            .and_then(|s| s.success()),
+        });
+        match task {
+            Some(task) => {
+                run_job(task);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+struct ThreadData {
+    shared: Arc<Shared>,
+    task_q: Worker<Job>, // local queue
+    index: usize,
+    idle_timeout: Duration,
+    task_cache: usize,
+    on_start: Option<ThreadHook>,
+    on_stop: Option<ThreadHook>,
+}
+
+thread_local! {
+    // Set while the current thread is running a pool's worker loop, so tasks
+    // can push new work on its local queue.
+    static CURRENT: RefCell<Option<ThreadData>> = const { RefCell::new(None) };
+}
+
+impl ThreadData {
+    fn spawn(self, builder: thread::Builder) -> io::Result<JoinHandle<()>> {
+        builder.spawn(move || self.run())
+    }
+
+    fn run(mut self) {
+        let shared = Arc::clone(&self.shared);
+        let index = self.index;
+        let idle_timeout = self.idle_timeout;
+        let on_stop = self.on_stop.take();
+        if self.task_cache > 0 {
+            slab::install(self.task_cache);
+        }
+        if let Some(on_start) = self.on_start.take() {
+            on_start(index);
+        }
+        CURRENT.with_borrow_mut(|current| *current = Some(self));
+        loop {
+            // The borrow must end before the task runs, it may spawn more work.
+            let task = CURRENT.with_borrow(|current| {
+                let data = current.as_ref().unwrap();
+                find_task(&data.task_q, &shared.injector, shared.stealers())
+            });
+            if let Some(task) = task {
+                run_job(task);
+                continue;
+            }
+            // Only leave once every queue is drained, so nothing spawned before
+            // shutdown is lost.
+            if shared.shutdown.load(Ordering::Acquire) {
+                break;
+            }
+            let guard = shared.sleep_lock.lock().unwrap();
+            if shared.injector.is_empty() && !shared.shutdown.load(Ordering::Acquire) {
+                let _ = shared.sleep_cvar.wait_timeout(guard, idle_timeout).unwrap();
+            }
+        }
+        CURRENT.with_borrow_mut(|current| *current = None);
+        slab::uninstall();
+        if let Some(on_stop) = on_stop {
+            on_stop(index);
+        }
+    }
+}
+
+pub(crate) fn run_task(task: Task) {
+    // A panicking task must not take its worker thread down with it.
+    let _ = panic::catch_unwind(AssertUnwindSafe(task));
+}
+
+fn run_job(job: Job) {
+    match job {
+        Job::Boxed(task) => run_task(task),
+        Job::Pooled(task) => {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| task.run()));
+        }
+    }
+}
+
+/// Order in which a thread pops the tasks of its own local queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueFlavor {
+    #[default]
+    Fifo,
+    /// Newest task first, friendlier to caches for recursive work.
+    Lifo,
+}
+
+/// Configuration of a `Factory`.
+///
+/// ```
+/// use crossbeam_newfifo01::pool::{PoolBuilder, QueueFlavor};
+///
+/// let pool = PoolBuilder::new()
+///     .num_threads(2)
+///     .thread_name("indexer")
+///     .queue_flavor(QueueFlavor::Lifo)
+///     .build()
+///     .unwrap();
+/// assert_eq!(pool.num_threads(), 2);
+/// ```
+#[derive(Clone)]
+pub struct PoolBuilder {
+    num_threads: usize,
+    thread_name: String,
+    stack_size: Option<usize>,
+    flavor: QueueFlavor,
+    on_thread_start: Option<ThreadHook>,
+    on_thread_stop: Option<ThreadHook>,
+    idle_timeout: Duration,
+    task_cache: usize,
+}
+
+impl Default for PoolBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PoolBuilder {
+    /// One thread per available CPU, FIFO local queues.
+    pub fn new() -> Self {
+        Self {
+            num_threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            thread_name: "factory-worker".to_string(),
+            stack_size: None,
+            flavor: QueueFlavor::Fifo,
+            on_thread_start: None,
+            on_thread_stop: None,
+            idle_timeout: IDLE_WAIT,
+            task_cache: 0,
+        }
+    }
+
+    pub fn num_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads;
+        self
+    }
+
+    /// Threads are named `{prefix}-{index}`.
+    pub fn thread_name(mut self, prefix: impl Into<String>) -> Self {
+        self.thread_name = prefix.into();
+        self
+    }
+
+    /// Stack size of each thread in bytes, the std default otherwise.
+    pub fn stack_size(mut self, bytes: usize) -> Self {
+        self.stack_size = Some(bytes);
+        self
+    }
+
+    pub fn queue_flavor(mut self, flavor: QueueFlavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Called on every worker thread before it runs its first task.
+    pub fn on_thread_start<F>(mut self, f: F) -> Self
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.on_thread_start = Some(Arc::new(f));
+        self
+    }
+
+    /// Called on every worker thread once it stopped taking tasks.
+    pub fn on_thread_stop<F>(mut self, f: F) -> Self
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.on_thread_stop = Some(Arc::new(f));
+        self
+    }
+
+    /// How long an idle thread sleeps before it checks the queues again when
+    /// nobody woke it up.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Recycle task allocations: every worker keeps up to `nodes` free task
+    /// nodes, off (0) by default. Tasks spawned from a worker thread whose
+    /// closure fits in 64 bytes are stored in one of them instead of their own
+    /// `Box`, and the node goes back to that worker once the task ran,
+    /// whichever thread ran it.
+    pub fn task_cache(mut self, nodes: usize) -> Self {
+        self.task_cache = nodes;
+        self
+    }
+
+    pub fn build(self) -> io::Result<Factory> {
+        self.build_into(Factory::new())
+    }
+
+    fn build_into(self, mut factory: Factory) -> io::Result<Factory> {
+        let task_qs: Vec<Worker<Job>> = (0..self.num_threads)
+            .map(|_| match self.flavor {
+                QueueFlavor::Fifo => Worker::new_fifo(),
+                QueueFlavor::Lifo => Worker::new_lifo(),
+            })
+            .collect();
+        let stealers = task_qs.iter().map(|q| q.stealer()).collect();
+        // Tasks spawned before this point are already on the injector and
+        // will be picked up by the new threads.
+        factory
+            .shared
+            .stealers
+            .set(stealers)
+            .map_err(|_| io::Error::other("thread pool already built"))?;
+
+        // launch threads and save workers
+        for (index, task_q) in task_qs.into_iter().enumerate() {
+            let mut builder = thread::Builder::new().name(format!("{}-{index}", self.thread_name));
+            if let Some(size) = self.stack_size {
+                builder = builder.stack_size(size);
+            }
+            let thread = ThreadData {
+                shared: Arc::clone(&factory.shared),
+                task_q,
+                index,
+                idle_timeout: self.idle_timeout,
+                task_cache: self.task_cache,
+                on_start: self.on_thread_start.clone(),
+                on_stop: self.on_thread_stop.clone(),
+            };
+            factory.workers.push(thread.spawn(builder)?);
+        }
+        Ok(factory)
+    }
+}
+
+/// Work-stealing thread pool.
+///
+/// Tasks are pushed on a global `Injector`; every thread owns a `Worker`
+/// queue and steals from the injector or from the other threads when it runs
+/// out of work. See `PoolBuilder` for the available settings.
+pub struct Factory {
+    shared: Arc<Shared>, // owner of the global queue
+    workers: Vec<JoinHandle<()>>,
+    keyed: Arc<KeyedQueues>,
+}
+
+impl Default for Factory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Factory {
+    /// Create a pool without threads, call `build_threadpool` to start them.
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared::new()),
+            workers: Vec::new(),
+            keyed: Arc::default(),
+        }
+    }
+
+    pub fn builder() -> PoolBuilder {
+        PoolBuilder::new()
+    }
+
+    /// Start `num_threads` worker threads with the default settings. Tasks
+    /// spawned before the pool was built are handed over to the new threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pool was already built or a thread can't be spawned.
+    pub fn build_threadpool(self, num_threads: usize) -> Self {
+        PoolBuilder::new()
+            .num_threads(num_threads)
+            .build_into(self)
+            .expect("failed to spawn worker thread")
+    }
+
+    /// Number of worker threads.
+    pub fn num_threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Schedule `f` to run on one of the worker threads.
+    ///
+    /// Called from one of this pool's tasks, `f` goes on the local queue of
+    /// the current thread, otherwise on the global queue.
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.push(Job::new(f));
+    }
+
+    /// Run `a` on the calling thread while `b` is offered to the pool, and
+    /// return both results. If no other thread picked `b` up by the time `a`
+    /// is done, `b` runs on the calling thread too.
+    ///
+    /// A panic in either closure is propagated once both have finished.
+    pub fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        self.shared.join(a, b)
+    }
+
+    fn push(&self, job: Job) {
+        push(&self.shared, job);
+    }
+
+    /// Handle to spawn on this pool without borrowing it.
+    pub fn spawner(&self) -> Spawner {
+        Spawner {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    pub(crate) fn keyed(&self) -> &Arc<KeyedQueues> {
+        &self.keyed
+    }
+
+    /// Run one queued task on the calling thread instead of blocking it.
+    /// Returns `false` if there was nothing to run.
+    pub fn try_run_one(&self) -> bool {
+        self.shared.try_run_one()
+    }
+
+    /// Wait until every queued task has run, then stop the threads.
+    fn shutdown(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        self.shared.notify_all();
+        for thread in self.workers.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Factory {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Spawn/join API shared by `Factory` and `SimulatedPool`, so code under
+/// test can run on either one.
+pub trait Executor {
+    fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static;
+
+    fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send;
+}
+
+impl Executor for Factory {
+    fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        Factory::spawn(self, f)
+    }
+
+    fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        Factory::join(self, a, b)
+    }
+}
+
+// Local queue of the current thread if it belongs to this pool, else the injector.
+fn push(shared: &Arc<Shared>, job: Job) {
+    let job = CURRENT.with_borrow(|current| match current {
+        Some(data) if Arc::ptr_eq(&data.shared, shared) => {
+            data.task_q.push(job);
+            None
+        }
+        _ => Some(job),
+    });
+    if let Some(job) = job {
+        shared.injector.push(job);
+    }
+    shared.notify_one();
+}
+
+/// Cloneable handle spawning tasks on a `Factory` from any thread. Tasks
+/// spawned after the pool was dropped never run.
+#[derive(Clone)]
+pub struct Spawner {
+    shared: Arc<Shared>,
+}
+
+impl Spawner {
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        push(&self.shared, Job::new(f));
+    }
+
+    /// `Factory::join` on the spawner's pool.
+    pub fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        self.shared.join(a, b)
+    }
+
+    /// `Factory::try_run_one` on the spawner's pool.
+    pub fn try_run_one(&self) -> bool {
+        self.shared.try_run_one()
+    }
+
+    /// Push a task borrowing from the caller's stack.
+    ///
+    /// # Safety
+    ///
+    /// The caller must not let anything `task` borrows go away before the
+    /// task has run.
+    pub(crate) unsafe fn spawn_unchecked<'a>(&self, task: Box<dyn FnOnce() + Send + 'a>) {
+        let task: Task = mem::transmute(task);
+        push(&self.shared, Job::Boxed(task));
+    }
+
+    /// Push on the global queue even from a worker thread, behind the work
+    /// already queued there.
+    pub(crate) fn inject(&self, task: Task) {
+        self.shared.injector.push(Job::Boxed(task));
+        self.shared.notify_one();
+    }
+}
+
+struct JoinJob<F, R> {
+    func: UnsafeCell<Option<F>>,
+    result: UnsafeCell<Option<thread::Result<R>>>,
+    done: AtomicBool,
+}
+
+// SAFETY: `func` is only taken by whoever wins the claim in `join`, and
+// `result` is only read after `done` was set with release ordering.
+unsafe impl<F: Send, R: Send> Sync for JoinJob<F, R> {}
+
+impl<F: FnOnce() -> R, R> JoinJob<F, R> {
+    fn run(&self) {
+        // SAFETY: see the `Sync` impl, this is the only access until `done`.
+        unsafe {
+            let func = (*self.func.get()).take().unwrap();
+            *self.result.get() = Some(panic::catch_unwind(AssertUnwindSafe(func)));
+        }
+        self.done.store(true, Ordering::Release);
+    }
+}
+
+struct JobRef<F, R>(*const JoinJob<F, R>);
+
+// SAFETY: the pointer is only followed from `JoinJob`'s own synchronisation.
+unsafe impl<F: Send, R: Send> Send for JobRef<F, R> {}
+
+impl<F: FnOnce() -> R, R> JobRef<F, R> {
+    /// # Safety
+    ///
+    /// The job must still be alive and not run yet.
+    unsafe fn run(self) {
+        (*self.0).run()
+    }
+}
+
+/// Handle to the pool whose worker thread this is, if any.
+pub(crate) fn current_worker() -> Option<Spawner> {
+    CURRENT.with_borrow(|current| {
+        current.as_ref().map(|data| Spawner {
+            shared: Arc::clone(&data.shared),
+        })
+    })
+}
+
+/// Push `f` on the local queue of the current worker thread. It runs on this
+/// thread later on, unless an idle thread steals it first.
+///
+/// # Panics
+///
+/// Panics if the current thread is not a worker thread of a `Factory`.
+pub fn spawn_local<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    let shared = CURRENT.with_borrow(|current| {
+        let data = current
+            .as_ref()
+            .expect("spawn_local called outside of a worker thread");
+        data.task_q.push(Job::new(f));
+        Arc::clone(&data.shared)
+    });
+    shared.notify_one();
+}
+
+/// Look for the next task: the local queue first, then a batch from the
+/// global queue, then a single task stolen from another thread.
+pub fn find_task<T>(local: &Worker<T>, global: &Injector<T>, stealers: &[Stealer<T>]) -> Option<T> {
+    // Pop a task from the local queue, if not empty.
+    local.pop().or_else(|| {
+        // Otherwise, we need to look for a task elsewhere.
+        iter::repeat_with(|| {
+            // Try stealing a batch of tasks from the global queue.
+            global
+                .steal_batch_and_pop(local)
+                // Or try stealing a task from one of the other threads.
+                .or_else(|| stealers.iter().map(|s| s.steal()).collect())
+        })
+        // Loop while no task was stolen and any steal operation needs to be retried.
+        .find(|s| !s.is_retry())
+        // Extract the stolen task, if there is one.
+        .and_then(|s| s.success())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn runs_every_task_before_drop_returns() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = Factory::new().build_threadpool(3);
+        for _ in 0..1000 {
+            let counter = Arc::clone(&counter);
+            pool.spawn(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        drop(pool);
+        assert_eq!(counter.load(Ordering::SeqCst), 1000);
+    }
+
+    #[test]
+    fn tasks_spawned_before_build_are_kept() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = Factory::new();
+        let c = Arc::clone(&counter);
+        pool.spawn(move || {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        let pool = pool.build_threadpool(2);
+        assert_eq!(pool.num_threads(), 2);
+        drop(pool);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn panicking_task_does_not_kill_the_worker() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = Factory::new().build_threadpool(1);
+        pool.spawn(|| panic!("boom"));
+        let c = Arc::clone(&counter);
+        pool.spawn(move || {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        drop(pool);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn spawn_local_from_a_task() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let wg = crossbeam::sync::WaitGroup::new();
+        let pool = Factory::new().build_threadpool(2);
+        let c = Arc::clone(&counter);
+        let task_wg = wg.clone();
+        pool.spawn(move || {
+            for _ in 0..10 {
+                let c = Arc::clone(&c);
+                let wg = task_wg.clone();
+                spawn_local(move || {
+                    c.fetch_add(1, Ordering::SeqCst);
+                    drop(wg);
+                });
+            }
+        });
+        wg.wait();
+        assert_eq!(counter.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of a worker thread")]
+    fn spawn_local_outside_the_pool_panics() {
+        spawn_local(|| {});
+    }
+
+    #[test]
+    fn try_run_one_on_the_calling_thread() {
+        let pool = Factory::new();
+        let (tx, rx) = crossbeam::channel::unbounded();
+        pool.spawn(move || tx.send(std::thread::current().id()).unwrap());
+        assert!(pool.try_run_one());
+        assert!(!pool.try_run_one());
+        assert_eq!(rx.recv().unwrap(), std::thread::current().id());
+    }
+
+    #[test]
+    fn join_borrows_from_the_stack() {
+        fn sum(pool: &Factory, v: &[u64]) -> u64 {
+            if v.len() <= 8 {
+                return v.iter().sum();
+            }
+            let (left, right) = v.split_at(v.len() / 2);
+            let (a, b) = pool.join(|| sum(pool, left), || sum(pool, right));
+            a + b
+        }
+        let v: Vec<u64> = (0..10_000).collect();
+        let pool = Factory::new().build_threadpool(3);
+        assert_eq!(sum(&pool, &v), v.iter().sum());
+        // Without threads the caller runs both sides.
+        assert_eq!(sum(&Factory::new(), &v), v.iter().sum());
+    }
+
+    #[test]
+    #[should_panic(expected = "right side")]
+    fn join_propagates_panics() {
+        let pool = Factory::new().build_threadpool(2);
+        pool.join(|| 1, || -> i32 { panic!("right side") });
+    }
+
+    #[test]
+    fn builder_names_threads_and_calls_hooks() {
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let (s, st) = (Arc::clone(&started), Arc::clone(&stopped));
+        let pool = PoolBuilder::new()
+            .num_threads(3)
+            .thread_name("sorter")
+            .stack_size(256 * 1024)
+            .idle_timeout(Duration::from_millis(1))
+            .on_thread_start(move |index| {
+                let name = thread::current().name().unwrap().to_string();
+                s.lock().unwrap().push((index, name));
+            })
+            .on_thread_stop(move |_| {
+                st.fetch_add(1, Ordering::SeqCst);
+            })
+            .build()
+            .unwrap();
+        let (tx, rx) = crossbeam::channel::unbounded();
+        pool.spawn(move || {
+            tx.send(thread::current().name().unwrap().to_string())
+                .unwrap()
+        });
+        assert!(rx.recv().unwrap().starts_with("sorter-"));
+        drop(pool);
+
+        let mut started = started.lock().unwrap().clone();
+        started.sort();
+        assert_eq!(
+            started,
+            vec![
+                (0, "sorter-0".to_string()),
+                (1, "sorter-1".to_string()),
+                (2, "sorter-2".to_string()),
+            ]
+        );
+        assert_eq!(stopped.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn lifo_queue_runs_newest_local_task_first() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let pool = PoolBuilder::new()
+            .num_threads(1)
+            .queue_flavor(QueueFlavor::Lifo)
+            .build()
+            .unwrap();
+        let o = Arc::clone(&order);
+        pool.spawn(move || {
+            for i in 0..3 {
+                let o = Arc::clone(&o);
+                spawn_local(move || o.lock().unwrap().push(i));
+            }
+        });
+        drop(pool);
+        assert_eq!(*order.lock().unwrap(), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn task_cache_runs_nested_spawns() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = PoolBuilder::new()
+            .num_threads(3)
+            .task_cache(16)
+            .build()
+            .unwrap();
+        for _ in 0..10 {
+            let (spawner, counter) = (pool.spawner(), Arc::clone(&counter));
+            pool.spawn(move || {
+                for i in 0..100 {
+                    let counter = Arc::clone(&counter);
+                    // Every 10th task panics, its node must still be recycled.
+                    spawner.spawn(move || {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        assert!(i % 10 != 0);
+                    });
+                }
+            });
+        }
+        drop(pool);
+        assert_eq!(counter.load(Ordering::SeqCst), 1000);
+    }
+
+    #[test]
+    fn find_task_prefers_local_queue() {
+        let local = Worker::new_fifo();
+        let global = Injector::new();
+        local.push(1);
+        global.push(2);
+        assert_eq!(find_task(&local, &global, &[]), Some(1));
+        assert_eq!(find_task(&local, &global, &[]), Some(2));
+        assert_eq!(find_task(&local, &global, &[]), None);
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crossbeam::{
+    channel::{self, Sender},
+    sync::WaitGroup,
+};
+
+use crate::pool::{spawn_local, Factory};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub root: PathBuf,
+    pub threads: usize,
+    /// Read every file and compute its FNV-1a hash.
+    pub hash: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub files: u64,
+    pub dirs: u64,
+    pub bytes: u64,
+    pub errors: u64,
+    /// `(path, hash)` of every file sorted by path, empty unless hashing was asked.
+    pub hashes: Vec<(PathBuf, u64)>,
+    pub elapsed: Duration,
+}
+
+#[derive(Default)]
+struct Counters {
+    files: AtomicU64,
+    dirs: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+}
+
+#[derive(Clone)]
+struct Ctx {
+    counters: Arc<Counters>,
+    hashes: Option<Sender<(PathBuf, u64)>>,
+    // Only held, every directory task drops its clone when done.
+    _wg: WaitGroup,
+}
+
+/// Walk `options.root` on a work-stealing pool. Every directory is a task on
+/// the local queue of the thread that found it, idle threads steal them.
+pub fn scan(options: &ScanOptions) -> ScanReport {
+    let start = Instant::now();
+    let counters = Arc::new(Counters::default());
+    let (tx, rx) = channel::unbounded();
+    let wg = WaitGroup::new();
+    let ctx = Ctx {
+        counters: Arc::clone(&counters),
+        hashes: options.hash.then_some(tx),
+        _wg: wg.clone(),
+    };
+
+    let pool = Factory::new().build_threadpool(options.threads.max(1));
+    let root = options.root.clone();
+    pool.spawn(move || visit_dir(root, ctx));
+    wg.wait();
+    drop(pool);
+
+    let mut hashes: Vec<(PathBuf, u64)> = rx.into_iter().collect();
+    hashes.sort();
+    ScanReport {
+        files: counters.files.load(Ordering::Relaxed),
+        dirs: counters.dirs.load(Ordering::Relaxed),
+        bytes: counters.bytes.load(Ordering::Relaxed),
+        errors: counters.errors.load(Ordering::Relaxed),
+        hashes,
+        elapsed: start.elapsed(),
+    }
+}
+
+fn visit_dir(dir: PathBuf, ctx: Ctx) {
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            ctx.counters.errors.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+    };
+    ctx.counters.dirs.fetch_add(1, Ordering::Relaxed);
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => {
+                ctx.counters.errors.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+        };
+        // DirEntry::file_type doesn't follow symlinks, so links are skipped
+        // instead of looping forever.
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => {
+                let ctx = ctx.clone();
+                spawn_local(move || visit_dir(entry.path(), ctx));
+            }
+            Ok(t) if t.is_file() => visit_file(&entry.path(), &ctx),
+            Ok(_) => {}
+            Err(_) => {
+                ctx.counters.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+fn visit_file(path: &Path, ctx: &Ctx) {
+    let len = match fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(_) => {
+            ctx.counters.errors.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+    };
+    if let Some(hashes) = &ctx.hashes {
+        match hash_file(path) {
+            Ok(hash) => {
+                let _ = hashes.send((path.to_path_buf(), hash));
+            }
+            Err(_) => {
+                ctx.counters.errors.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+    ctx.counters.files.fetch_add(1, Ordering::Relaxed);
+    ctx.counters.bytes.fetch_add(len, Ordering::Relaxed);
+}
+
+/// 64-bit FNV-1a hash of the file content.
+pub fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut hash = FNV_OFFSET;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hash);
+        }
+        hash = fnv1a(hash, &buf[..n]);
+    }
+}
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("newfifo01-scan-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for i in 0..5 {
+            let dir = root.join(format!("d{i}")).join("inner");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("a.txt"), "hello").unwrap();
+            fs::write(root.join(format!("d{i}")).join("b.txt"), vec![b'x'; i * 10]).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn fnv1a_known_values() {
+        assert_eq!(fnv1a(FNV_OFFSET, b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn counts_files_dirs_and_bytes() {
+        let root = fixture("count");
+        let report = scan(&ScanOptions {
+            root: root.clone(),
+            threads: 4,
+            hash: false,
+        });
+        assert_eq!(report.files, 10);
+        assert_eq!(report.dirs, 11);
+        assert_eq!(report.bytes, 5 * 5 + (10 + 20 + 30 + 40));
+        assert_eq!(report.errors, 0);
+        assert!(report.hashes.is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn hashes_are_sorted_and_stable() {
+        let root = fixture("hash");
+        let options = ScanOptions {
+            root: root.clone(),
+            threads: 3,
+            hash: true,
+        };
+        let first = scan(&options);
+        let second = scan(&ScanOptions {
+            threads: 1,
+            ..options
+        });
+        assert_eq!(first.hashes.len(), 10);
+        assert_eq!(first.hashes, second.hashes);
+        let hello = root.join("d0").join("inner").join("a.txt");
+        let (_, hash) = first.hashes.iter().find(|(p, _)| *p == hello).unwrap();
+        assert_eq!(*hash, fnv1a(FNV_OFFSET, b"hello"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn missing_root_is_an_error() {
+        let report = scan(&ScanOptions {
+            root: PathBuf::from("/definitely/not/here"),
+            threads: 2,
+            hash: false,
+        });
+        assert_eq!(report.dirs, 0);
+        assert_eq!(report.errors, 1);
+    }
+}
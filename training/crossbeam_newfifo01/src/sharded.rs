@@ -0,0 +1,365 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::{BuildHasher, Hash, RandomState},
+    ops::{Deref, DerefMut},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    thread,
+};
+
+use crossbeam::utils::CachePadded;
+
+use crate::{global, pool::Factory};
+
+type Shard<K, V, S> = CachePadded<RwLock<HashMap<K, V, S>>>;
+
+/// Concurrent hash map split in independently locked shards.
+///
+/// A key's shard is picked from its hash, so threads working on different
+/// keys rarely wait for each other. Every shard sits on its own cache line.
+///
+/// ```
+/// use crossbeam_newfifo01::sharded::ShardedMap;
+///
+/// let map = ShardedMap::new();
+/// for word in "a b a c a".split(' ') {
+///     *map.entry(word).or_insert(0) += 1;
+/// }
+/// assert_eq!(map.get("a"), Some(3));
+/// assert_eq!(map.len(), 3);
+/// ```
+pub struct ShardedMap<K, V, S = RandomState> {
+    shards: Box<[Shard<K, V, S>]>,
+    hasher: S,
+    shift: u32,
+}
+
+impl<K: Hash + Eq, V> ShardedMap<K, V> {
+    /// Four shards per available CPU.
+    pub fn new() -> Self {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(cpus * 4)
+    }
+
+    /// `shards` is rounded up to a power of two.
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq, V> Default for ShardedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> ShardedMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> Self {
+        let shards = shards.max(1).next_power_of_two();
+        Self {
+            shards: (0..shards)
+                .map(|_| CachePadded::new(RwLock::new(HashMap::with_hasher(hasher.clone()))))
+                .collect(),
+            hasher,
+            shift: shards.trailing_zeros(),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard<Q>(&self, key: &Q) -> &RwLock<HashMap<K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        if self.shift == 0 {
+            return &self.shards[0];
+        }
+        // The shard maps hash with the same hasher and use its top 7 bits as
+        // tags, so the shard index comes from the bits right below them.
+        let hash = self.hasher.hash_one(key);
+        let index = (hash << 7) >> (u64::BITS - self.shift);
+        &self.shards[index as usize]
+    }
+
+    fn read<Q>(&self, key: &Q) -> RwLockReadGuard<'_, HashMap<K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        // A panic while a shard was locked leaves its map usable.
+        self.shard(key).read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write<Q>(&self, key: &Q) -> RwLockWriteGuard<'_, HashMap<K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        self.shard(key).write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.write(&key).insert(key, value)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.write(key).remove(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read(key).contains_key(key)
+    }
+
+    /// A copy of the value, the shard isn't kept locked.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.read(key).get(key).cloned()
+    }
+
+    /// Call `f` on the value under the shard's read lock.
+    pub fn with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read(key).get(key).map(f)
+    }
+
+    /// Entry for in-place updates. The key's shard stays write-locked until
+    /// the entry, or the reference it returns, is dropped.
+    pub fn entry(&self, key: K) -> Entry<'_, K, V, S> {
+        Entry {
+            guard: self.write(&key),
+            key,
+        }
+    }
+
+    /// Total number of entries. Shards are counted one after the other, so
+    /// the result is approximate while other threads write.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap_or_else(|e| e.into_inner()).len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.write().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+}
+
+impl<K, V, S> ShardedMap<K, V, S>
+where
+    K: Hash + Eq + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    /// Call `f` on every entry, one task per shard on `pool`. Each shard is
+    /// read-locked while its task runs.
+    pub fn par_for_each<F>(&self, pool: &Factory, f: F)
+    where
+        F: Fn(&K, &V) + Sync,
+    {
+        let f = &f;
+        global::install(pool, || {
+            global::scope(|s| {
+                for shard in self.shards.iter() {
+                    s.spawn(move |_| {
+                        let shard = shard.read().unwrap_or_else(|e| e.into_inner());
+                        shard.iter().for_each(|(k, v)| f(k, v));
+                    });
+                }
+            })
+        });
+    }
+
+    /// Keep only the entries for which `f` returns `true`, one task per shard
+    /// on `pool`. Each shard is write-locked while its task runs.
+    pub fn par_retain<F>(&self, pool: &Factory, f: F)
+    where
+        F: Fn(&K, &mut V) -> bool + Sync,
+    {
+        let f = &f;
+        global::install(pool, || {
+            global::scope(|s| {
+                for shard in self.shards.iter() {
+                    s.spawn(move |_| {
+                        let mut shard = shard.write().unwrap_or_else(|e| e.into_inner());
+                        shard.retain(|k, v| f(k, v));
+                    });
+                }
+            })
+        });
+    }
+}
+
+/// A key's slot in a `ShardedMap`, occupied or not.
+pub struct Entry<'a, K, V, S> {
+    guard: RwLockWriteGuard<'a, HashMap<K, V, S>>,
+    key: K,
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Update the value in place if there is one.
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Some(value) = self.guard.get_mut(&self.key) {
+            f(value);
+        }
+        self
+    }
+
+    pub fn or_insert(self, default: V) -> RefMut<'a, K, V, S> {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(mut self, default: impl FnOnce() -> V) -> RefMut<'a, K, V, S> {
+        let value: *mut V = self.guard.entry(self.key).or_insert_with(default);
+        RefMut {
+            _guard: self.guard,
+            value,
+        }
+    }
+
+    pub fn or_default(self) -> RefMut<'a, K, V, S>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+/// Mutable reference to a value, holding its shard's write lock.
+pub struct RefMut<'a, K, V, S> {
+    _guard: RwLockWriteGuard<'a, HashMap<K, V, S>>,
+    // Points into the locked map, which can't change while `_guard` is held.
+    value: *mut V,
+}
+
+impl<K, V, S> Deref for RefMut<'_, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        // SAFETY: see `value`.
+        unsafe { &*self.value }
+    }
+}
+
+impl<K, V, S> DerefMut for RefMut<'_, K, V, S> {
+    fn deref_mut(&mut self) -> &mut V {
+        // SAFETY: see `value`, and `&mut self` makes this the only reference.
+        unsafe { &mut *self.value }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn basic_operations() {
+        let map = ShardedMap::with_shards(5);
+        assert_eq!(map.shard_count(), 8);
+        assert!(map.is_empty());
+        assert_eq!(map.insert("a".to_string(), 1), None);
+        assert_eq!(map.insert("a".to_string(), 2), Some(1));
+        map.insert("b".to_string(), 3);
+        assert_eq!(map.get("a"), Some(2));
+        assert_eq!(map.with("b", |v| v * 10), Some(30));
+        assert!(map.contains_key("b"));
+        assert_eq!(map.remove("b"), Some(3));
+        assert_eq!(map.get("b"), None);
+        assert_eq!(map.len(), 1);
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn entry_api() {
+        let map: ShardedMap<&str, Vec<u32>> = ShardedMap::with_shards(1);
+        map.entry("x").or_default().push(1);
+        map.entry("x").or_insert_with(|| vec![0]).push(2);
+        let e = map.entry("x").and_modify(|v| v.push(3));
+        assert_eq!(*e.key(), "x");
+        drop(e);
+        map.entry("y").and_modify(|v| v.push(9)).or_insert(vec![0]);
+        assert_eq!(map.get("x"), Some(vec![1, 2, 3]));
+        assert_eq!(map.get("y"), Some(vec![0]));
+    }
+
+    #[test]
+    fn concurrent_counters() {
+        let map = Arc::new(ShardedMap::new());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    for i in 0..10_000u64 {
+                        *map.entry(i % 100).or_insert(0) += 1;
+                    }
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+        assert_eq!(map.len(), 100);
+        assert!((0..100).all(|k| map.get(&k) == Some(400)));
+    }
+
+    #[test]
+    fn parallel_for_each_and_retain() {
+        let pool = Factory::new().build_threadpool(4);
+        let map = ShardedMap::with_shards(16);
+        for i in 0..1000u64 {
+            map.insert(i, i * 2);
+        }
+        let sum = AtomicU64::new(0);
+        map.par_for_each(&pool, |_, v| {
+            sum.fetch_add(*v, Ordering::Relaxed);
+        });
+        assert_eq!(sum.load(Ordering::Relaxed), 999 * 1000);
+
+        map.par_retain(&pool, |k, v| {
+            *v += 1;
+            k % 3 == 0
+        });
+        assert_eq!(map.len(), 334);
+        assert_eq!(map.get(&3), Some(7));
+        assert_eq!(map.get(&4), None);
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    sync::Mutex,
+    thread,
+};
+
+use crate::pool::{Executor, Task};
+
+/// Virtual worker threads of `SimulatedPool::new`.
+const DEFAULT_WORKERS: usize = 4;
+
+/// Where a simulated worker found the task it ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Local,
+    Injector,
+    Stolen { from: usize },
+}
+
+/// One scheduling decision of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub worker: usize,
+    pub source: Source,
+}
+
+/// SplitMix64, small and good enough to pick interleavings.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+struct State {
+    rng: Rng,
+    injector: VecDeque<Task>,
+    locals: Vec<VecDeque<Task>>,
+    current: Option<usize>,
+    trace: Vec<Step>,
+}
+
+/// Single-threaded stand-in for `Factory` with a reproducible schedule.
+///
+/// Tasks are queued like on the real pool, on the injector or on the local
+/// queue of the virtual worker running the spawning task. A seeded RNG picks
+/// which worker runs next and which victim it steals from, so the same seed
+/// always gives the same interleaving.
+///
+/// ```
+/// use crossbeam_newfifo01::{pool::Executor, sim::SimulatedPool};
+///
+/// let pool = SimulatedPool::new(42);
+/// let (a, b) = pool.join(|| 1, || 2);
+/// assert_eq!(a + b, 3);
+/// ```
+pub struct SimulatedPool {
+    seed: u64,
+    state: Mutex<State>,
+}
+
+impl SimulatedPool {
+    pub fn new(seed: u64) -> Self {
+        Self::with_workers(seed, DEFAULT_WORKERS)
+    }
+
+    pub fn with_workers(seed: u64, num_workers: usize) -> Self {
+        Self {
+            seed,
+            state: Mutex::new(State {
+                rng: Rng(seed),
+                injector: VecDeque::new(),
+                locals: (0..num_workers.max(1)).map(|_| VecDeque::new()).collect(),
+                current: None,
+                trace: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Every scheduling decision taken so far.
+    pub fn trace(&self) -> Vec<Step> {
+        self.state.lock().unwrap().trace.clone()
+    }
+
+    /// Run tasks until every queue is empty, returns how many ran.
+    pub fn run_until_idle(&self) -> usize {
+        let mut count = 0;
+        while self.step() {
+            count += 1;
+        }
+        count
+    }
+
+    /// Run one task picked by the RNG, `false` if there was none.
+    pub fn step(&self) -> bool {
+        let next = {
+            let state = &mut *self.state.lock().unwrap();
+            let worker = state.rng.below(state.locals.len());
+            let next = if let Some(task) = state.locals[worker].pop_front() {
+                Some((task, Source::Local))
+            } else if let Some(task) = state.injector.pop_front() {
+                Some((task, Source::Injector))
+            } else {
+                let victims: Vec<usize> = (0..state.locals.len())
+                    .filter(|v| !state.locals[*v].is_empty())
+                    .collect();
+                if victims.is_empty() {
+                    None
+                } else {
+                    let from = victims[state.rng.below(victims.len())];
+                    let task = state.locals[from].pop_front().unwrap();
+                    Some((task, Source::Stolen { from }))
+                }
+            };
+            next.map(|(task, source)| {
+                state.trace.push(Step { worker, source });
+                (task, worker)
+            })
+        };
+        match next {
+            Some((task, worker)) => {
+                self.run_as(worker, task);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Run `task` as virtual worker `worker`. Unlike the real pool, a panic is
+    // not swallowed: it fails the test that owns the seed.
+    fn run_as(&self, worker: usize, task: Task) {
+        let prev = self.state.lock().unwrap().current.replace(worker);
+        let res = panic::catch_unwind(AssertUnwindSafe(task));
+        self.state.lock().unwrap().current = prev;
+        if let Err(payload) = res {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Run `test` once per seed on a fresh pool, which is then run until idle.
+    /// The panic of a failing seed is re-raised with the seed in the message,
+    /// so `SimulatedPool::new(seed)` replays it.
+    pub fn explore<F>(seeds: Range<u64>, test: F)
+    where
+        F: Fn(&SimulatedPool),
+    {
+        for seed in seeds {
+            let pool = SimulatedPool::new(seed);
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                test(&pool);
+                pool.run_until_idle();
+            }));
+            if let Err(payload) = res {
+                let msg = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                panic!("simulation failed with seed {seed}: {msg}");
+            }
+        }
+    }
+}
+
+impl Executor for SimulatedPool {
+    fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut state = self.state.lock().unwrap();
+        match state.current {
+            Some(worker) => state.locals[worker].push_back(Box::new(f)),
+            None => state.injector.push_back(Box::new(f)),
+        }
+    }
+
+    /// Run `a` and `b` in an RNG-picked order, with a few other queued tasks
+    /// squeezed in between as if they ran concurrently.
+    fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        let (b_first, between) = {
+            let mut state = self.state.lock().unwrap();
+            let b_first = state.rng.below(2) == 1;
+            (b_first, state.rng.below(3))
+        };
+        let interleave = || {
+            for _ in 0..between {
+                if !self.step() {
+                    break;
+                }
+            }
+        };
+        if b_first {
+            let rb = panic::catch_unwind(AssertUnwindSafe(b));
+            interleave();
+            let ra = panic::catch_unwind(AssertUnwindSafe(a));
+            unwrap_both(ra, rb)
+        } else {
+            let ra = panic::catch_unwind(AssertUnwindSafe(a));
+            interleave();
+            let rb = panic::catch_unwind(AssertUnwindSafe(b));
+            unwrap_both(ra, rb)
+        }
+    }
+}
+
+fn unwrap_both<RA, RB>(ra: thread::Result<RA>, rb: thread::Result<RB>) -> (RA, RB) {
+    match (ra, rb) {
+        (Ok(ra), Ok(rb)) => (ra, rb),
+        (Err(payload), _) | (_, Err(payload)) => panic::resume_unwind(payload),
+    }
+}
+
+impl fmt::Debug for SimulatedPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimulatedPool")
+            .field("seed", &self.seed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SimulatedPool {
+    // Same contract as `Factory`: queued tasks run before the pool goes away.
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.run_until_idle();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn record_order(seed: u64) -> (Vec<usize>, Vec<Step>) {
+        let pool = Arc::new(SimulatedPool::new(seed));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for i in 0..10 {
+            let (p, o) = (Arc::clone(&pool), Arc::clone(&order));
+            pool.spawn(move || {
+                o.lock().unwrap().push(i);
+                let o = Arc::clone(&o);
+                p.spawn(move || o.lock().unwrap().push(100 + i));
+            });
+        }
+        pool.run_until_idle();
+        let order = order.lock().unwrap().clone();
+        (order, pool.trace())
+    }
+
+    #[test]
+    fn same_seed_same_interleaving() {
+        let (order, trace) = record_order(7);
+        assert_eq!(order.len(), 20);
+        assert_eq!(trace.len(), 20);
+        assert_eq!(record_order(7), (order, trace));
+    }
+
+    #[test]
+    fn seeds_explore_different_interleavings() {
+        let orders: Vec<Vec<usize>> = (0..10).map(|seed| record_order(seed).0).collect();
+        assert!(orders.iter().any(|o| *o != orders[0]));
+    }
+
+    #[test]
+    fn locally_spawned_tasks_get_stolen() {
+        let pool = Arc::new(SimulatedPool::with_workers(3, 4));
+        let p = Arc::clone(&pool);
+        pool.spawn(move || {
+            for _ in 0..20 {
+                p.spawn(|| {});
+            }
+        });
+        pool.run_until_idle();
+        let trace = pool.trace();
+        assert_eq!(trace[0].source, Source::Injector);
+        assert!(trace
+            .iter()
+            .any(|s| matches!(s.source, Source::Stolen { .. })));
+    }
+
+    #[test]
+    fn join_runs_both_sides() {
+        fn sum(pool: &SimulatedPool, v: &[u64]) -> u64 {
+            if v.len() <= 2 {
+                return v.iter().sum();
+            }
+            let (l, r) = v.split_at(v.len() / 2);
+            let (a, b) = pool.join(|| sum(pool, l), || sum(pool, r));
+            a + b
+        }
+        let v: Vec<u64> = (0..100).collect();
+        for seed in 0..5 {
+            assert_eq!(sum(&SimulatedPool::new(seed), &v), 4950);
+        }
+    }
+
+    #[test]
+    fn explore_reports_the_failing_seed() {
+        let res = panic::catch_unwind(|| {
+            SimulatedPool::explore(0..50, |pool| {
+                let log = Arc::new(Mutex::new(Vec::new()));
+                for i in 0..3 {
+                    let log = Arc::clone(&log);
+                    pool.spawn(move || log.lock().unwrap().push(i));
+                }
+                pool.run_until_idle();
+                // Spawned from outside, so every task goes through the FIFO
+                // injector whatever the seed.
+                assert_eq!(*log.lock().unwrap(), vec![0, 1, 2]);
+            });
+        });
+        assert!(res.is_ok());
+
+        let res = panic::catch_unwind(|| {
+            SimulatedPool::explore(0..50, |pool| {
+                let order = Arc::new(Mutex::new(Vec::new()));
+                let (o1, o2) = (Arc::clone(&order), Arc::clone(&order));
+                pool.join(
+                    move || o1.lock().unwrap().push('a'),
+                    move || o2.lock().unwrap().push('b'),
+                );
+                assert_eq!(*order.lock().unwrap(), vec!['a', 'b'], "b ran first");
+            });
+        });
+        let msg = res.unwrap_err().downcast::<String>().unwrap();
+        assert!(msg.starts_with("simulation failed with seed "));
+        assert!(msg.contains("b ran first"));
+    }
+}
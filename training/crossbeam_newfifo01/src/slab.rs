@@ -0,0 +1,240 @@
+use std::{
+    cell::RefCell,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ptr,
+    sync::Arc,
+};
+
+use crossbeam::queue::ArrayQueue;
+
+/// Closures up to this many bytes fit in a recycled node, bigger ones are
+/// boxed as usual.
+pub(crate) const NODE_CAPACITY: usize = 64;
+
+#[repr(C, align(16))]
+struct Storage(MaybeUninit<[u8; NODE_CAPACITY]>);
+
+struct Node {
+    storage: Storage,
+    // Both set while `storage` holds a closure.
+    call: Option<unsafe fn(*mut Storage)>,
+    drop: Option<unsafe fn(*mut Storage)>,
+    // Set while the node is in use, so free nodes don't keep their home alive.
+    home: Option<Arc<Home>>,
+}
+
+/// Lock-free queue through which other threads return a worker's nodes.
+struct Home {
+    returned: ArrayQueue<Box<Node>>,
+}
+
+/// Free nodes of the current worker thread.
+struct LocalCache {
+    home: Arc<Home>,
+    // Boxed on purpose, nodes are handed out as the allocations they are.
+    #[allow(clippy::vec_box)]
+    free: Vec<Box<Node>>,
+    capacity: usize,
+}
+
+thread_local! {
+    // Set on worker threads of pools built with a task cache.
+    static CACHE: RefCell<Option<LocalCache>> = const { RefCell::new(None) };
+}
+
+/// Give the current thread a cache keeping up to `capacity` free nodes.
+pub(crate) fn install(capacity: usize) {
+    CACHE.with_borrow_mut(|cache| {
+        *cache = Some(LocalCache {
+            home: Arc::new(Home {
+                returned: ArrayQueue::new(capacity),
+            }),
+            free: Vec::with_capacity(capacity),
+            capacity,
+        })
+    });
+}
+
+/// Free the cached nodes. Nodes still in use are freed when they come back.
+pub(crate) fn uninstall() {
+    CACHE.with_borrow_mut(|cache| *cache = None);
+}
+
+/// A task stored in a node taken from a worker's cache.
+pub(crate) struct PooledTask(ManuallyDrop<Box<Node>>);
+
+// SAFETY: `alloc` only stores `Send` closures.
+unsafe impl Send for PooledTask {}
+
+/// Move `f` into a node of the current thread's cache. Gives `f` back if the
+/// thread has no cache or `f` doesn't fit in a node.
+pub(crate) fn alloc<F>(f: F) -> Result<PooledTask, F>
+where
+    F: FnOnce() + Send + 'static,
+{
+    if mem::size_of::<F>() > NODE_CAPACITY || mem::align_of::<F>() > mem::align_of::<Storage>() {
+        return Err(f);
+    }
+    let node = CACHE
+        .try_with(|cache| cache.borrow_mut().as_mut().map(LocalCache::take))
+        .ok()
+        .flatten();
+    let Some(mut node) = node else {
+        return Err(f);
+    };
+    // SAFETY: size and alignment were checked above.
+    unsafe { ptr::write(node.storage.0.as_mut_ptr().cast::<F>(), f) };
+    node.call = Some(call::<F>);
+    node.drop = Some(drop_in_place::<F>);
+    Ok(PooledTask(ManuallyDrop::new(node)))
+}
+
+impl LocalCache {
+    fn take(&mut self) -> Box<Node> {
+        if self.free.is_empty() {
+            while let Some(node) = self.home.returned.pop() {
+                self.free.push(node);
+            }
+        }
+        let mut node = self.free.pop().unwrap_or_else(|| {
+            Box::new(Node {
+                storage: Storage(MaybeUninit::uninit()),
+                call: None,
+                drop: None,
+                home: None,
+            })
+        });
+        node.home = Some(Arc::clone(&self.home));
+        node
+    }
+}
+
+impl PooledTask {
+    pub(crate) fn run(mut self) {
+        let node = &mut **self.0;
+        let call = node.call.take().expect("pooled task without a closure");
+        node.drop = None;
+        // SAFETY: `call` matches the closure type written by `alloc`, and
+        // clearing `drop` above makes sure it's not dropped a second time.
+        // If the closure panics, `self` is still dropped and recycled.
+        unsafe { call(&mut node.storage) };
+    }
+}
+
+impl Drop for PooledTask {
+    fn drop(&mut self) {
+        // SAFETY: `self.0` is not used after this.
+        let mut node = unsafe { ManuallyDrop::take(&mut self.0) };
+        if let Some(drop) = node.drop.take() {
+            // Never ran, e.g. still queued when the pool went away.
+            node.call = None;
+            // SAFETY: the storage still holds the closure written by `alloc`.
+            unsafe { drop(&mut node.storage) };
+        }
+        recycle(node);
+    }
+}
+
+// Straight to the free list on the allocating thread, through the home's
+// return queue from anywhere else. Nodes that don't fit anymore are freed.
+fn recycle(mut node: Box<Node>) {
+    let Some(home) = node.home.take() else {
+        return;
+    };
+    let mut node = Some(node);
+    let _ = CACHE.try_with(|cache| {
+        if let Some(cache) = cache.borrow_mut().as_mut() {
+            if Arc::ptr_eq(&cache.home, &home) && cache.free.len() < cache.capacity {
+                cache.free.extend(node.take());
+            }
+        }
+    });
+    if let Some(node) = node {
+        let _ = home.returned.push(node);
+    }
+}
+
+unsafe fn call<F: FnOnce()>(storage: *mut Storage) {
+    let f = ptr::read(storage.cast::<F>());
+    f()
+}
+
+unsafe fn drop_in_place<F>(storage: *mut Storage) {
+    ptr::drop_in_place(storage.cast::<F>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        panic::AssertUnwindSafe,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    fn free_nodes() -> usize {
+        CACHE.with_borrow(|cache| {
+            let cache = cache.as_ref().unwrap();
+            cache.free.len() + cache.home.returned.len()
+        })
+    }
+
+    #[test]
+    fn nodes_are_reused_on_the_same_thread() {
+        install(4);
+        let hits = Arc::new(AtomicUsize::new(0));
+        for _ in 0..10 {
+            let hits = Arc::clone(&hits);
+            let task = alloc(move || {
+                hits.fetch_add(1, Ordering::SeqCst);
+            })
+            .ok()
+            .unwrap();
+            task.run();
+            assert_eq!(free_nodes(), 1);
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 10);
+        uninstall();
+    }
+
+    #[test]
+    fn nodes_freed_elsewhere_come_back_through_the_queue() {
+        install(8);
+        let tasks: Vec<_> = (0..3).map(|_| alloc(|| {}).ok().unwrap()).collect();
+        thread::spawn(move || tasks.into_iter().for_each(PooledTask::run))
+            .join()
+            .unwrap();
+        assert_eq!(free_nodes(), 3);
+        uninstall();
+    }
+
+    #[test]
+    fn unrun_and_panicking_tasks_drop_their_closure() {
+        install(4);
+        let data = Arc::new(());
+        let d = Arc::clone(&data);
+        drop(alloc(move || drop(d)).ok().unwrap());
+        assert_eq!(Arc::strong_count(&data), 1);
+
+        let d = Arc::clone(&data);
+        let task = alloc(move || {
+            let _d = d;
+            panic!("boom");
+        })
+        .ok()
+        .unwrap();
+        assert!(std::panic::catch_unwind(AssertUnwindSafe(|| task.run())).is_err());
+        assert_eq!(Arc::strong_count(&data), 1);
+        assert_eq!(free_nodes(), 1);
+        uninstall();
+    }
+
+    #[test]
+    fn big_closures_and_threads_without_cache_are_refused() {
+        assert!(alloc(|| {}).is_err());
+        install(4);
+        let big = [0u8; NODE_CAPACITY + 1];
+        assert!(alloc(move || assert_eq!(big.len(), NODE_CAPACITY + 1)).is_err());
+        uninstall();
+    }
+}
@@ -0,0 +1,367 @@
+use std::cmp::Ordering;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+
+use crate::pool::Factory;
+
+/// Slices up to this length are sorted sequentially.
+pub const SEQUENTIAL_THRESHOLD: usize = 4096;
+
+/// Unstable parallel quicksort.
+pub fn par_sort<T: Ord + Send>(pool: &Factory, v: &mut [T]) {
+    par_sort_by(pool, v, T::cmp);
+}
+
+pub fn par_sort_by_key<T, K, F>(pool: &Factory, v: &mut [T], f: F)
+where
+    T: Send,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    par_sort_by(pool, v, |a, b| f(a).cmp(&f(b)));
+}
+
+pub fn par_sort_by<T, F>(pool: &Factory, v: &mut [T], compare: F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    // Past this depth the pivots are clearly bad, sort what's left sequentially.
+    let limit = 2 * (usize::BITS - v.len().leading_zeros());
+    quicksort(pool, v, &compare, limit);
+}
+
+/// Stable parallel merge sort, equal elements keep their order.
+pub fn par_sort_stable<T: Ord + Send>(pool: &Factory, v: &mut [T]) {
+    par_sort_stable_by(pool, v, T::cmp);
+}
+
+pub fn par_sort_stable_by_key<T, K, F>(pool: &Factory, v: &mut [T], f: F)
+where
+    T: Send,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    par_sort_stable_by(pool, v, |a, b| f(a).cmp(&f(b)));
+}
+
+pub fn par_sort_stable_by<T, F>(pool: &Factory, v: &mut [T], compare: F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if v.len() <= SEQUENTIAL_THRESHOLD {
+        v.sort_by(compare);
+        return;
+    }
+    let mut buf: Vec<MaybeUninit<T>> = Vec::with_capacity(v.len());
+    buf.resize_with(v.len(), MaybeUninit::uninit);
+    merge_sort(pool, v, &mut buf, &compare);
+}
+
+fn quicksort<T, F>(pool: &Factory, v: &mut [T], compare: &F, limit: u32)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if v.len() <= SEQUENTIAL_THRESHOLD || limit == 0 {
+        v.sort_unstable_by(compare);
+        return;
+    }
+    let (lt, gt) = partition(v, compare);
+    let (left, rest) = v.split_at_mut(lt);
+    let right = &mut rest[gt - lt..];
+    pool.join(
+        || quicksort(pool, left, compare, limit - 1),
+        || quicksort(pool, right, compare, limit - 1),
+    );
+}
+
+/// Three-way partition around a median-of-three pivot. Returns `(lt, gt)`
+/// with `v[..lt] < pivot`, `v[lt..gt] == pivot` and `v[gt..] > pivot`.
+fn partition<T, F>(v: &mut [T], compare: &F) -> (usize, usize)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let len = v.len();
+    let (a, b, c) = (0, len / 2, len - 1);
+    let pivot = if compare(&v[a], &v[b]).is_lt() {
+        if compare(&v[b], &v[c]).is_lt() {
+            b
+        } else if compare(&v[a], &v[c]).is_lt() {
+            c
+        } else {
+            a
+        }
+    } else if compare(&v[a], &v[c]).is_lt() {
+        a
+    } else if compare(&v[b], &v[c]).is_lt() {
+        c
+    } else {
+        b
+    };
+    v.swap(0, pivot);
+
+    // v[0] is the pivot, v[1..lt] < pivot, v[lt..i] == pivot, v[gt..] > pivot.
+    let (mut lt, mut i, mut gt) = (1, 1, len);
+    while i < gt {
+        match compare(&v[i], &v[0]) {
+            Ordering::Less => {
+                v.swap(lt, i);
+                lt += 1;
+                i += 1;
+            }
+            Ordering::Equal => i += 1,
+            Ordering::Greater => {
+                gt -= 1;
+                v.swap(i, gt);
+            }
+        }
+    }
+    v.swap(0, lt - 1);
+    (lt - 1, gt)
+}
+
+fn merge_sort<T, F>(pool: &Factory, v: &mut [T], buf: &mut [MaybeUninit<T>], compare: &F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    if v.len() <= SEQUENTIAL_THRESHOLD {
+        v.sort_by(compare);
+        return;
+    }
+    let mid = v.len() / 2;
+    {
+        let (left, right) = v.split_at_mut(mid);
+        let (left_buf, right_buf) = buf.split_at_mut(mid);
+        pool.join(
+            || merge_sort(pool, left, left_buf, compare),
+            || merge_sort(pool, right, right_buf, compare),
+        );
+    }
+    // Move the two sorted runs out to `buf` and merge them back into `v`.
+    // From here on `v` holds bitwise copies only, `par_merge` overwrites each
+    // slot exactly once, panic or not.
+    unsafe { ptr::copy_nonoverlapping(v.as_ptr(), buf.as_mut_ptr().cast::<T>(), v.len()) };
+    let (left, right) = buf.split_at_mut(mid);
+    par_merge(pool, left, right, v, compare);
+}
+
+/// Merge the sorted runs `left` and `right`, both initialized, into `dest`.
+/// Big merges are split in two independent ones around the middle element of
+/// the longer run, found in the other run by binary search.
+fn par_merge<T, F>(
+    pool: &Factory,
+    left: &mut [MaybeUninit<T>],
+    right: &mut [MaybeUninit<T>],
+    dest: &mut [T],
+    compare: &F,
+) where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    debug_assert_eq!(left.len() + right.len(), dest.len());
+    if dest.len() <= SEQUENTIAL_THRESHOLD || left.is_empty() || right.is_empty() {
+        merge(left, right, dest, compare);
+        return;
+    }
+    // Puts everything in `dest` if a comparison of the search panics.
+    let hole = MergeHole::new(left, right, dest);
+    let (l, r) = unsafe {
+        if left.len() >= right.len() {
+            // Equal elements of `right` go after the pivot, they come from the right run
+            let l = left.len() / 2;
+            let pivot = left[l].assume_init_ref();
+            let r = right.partition_point(|x| compare(x.assume_init_ref(), pivot).is_lt());
+            (l, r)
+        } else {
+            let r = right.len() / 2;
+            let pivot = right[r].assume_init_ref();
+            let l = left.partition_point(|x| compare(x.assume_init_ref(), pivot).is_le());
+            (l, r)
+        }
+    };
+    mem::forget(hole);
+    let (left_low, left_high) = left.split_at_mut(l);
+    let (right_low, right_high) = right.split_at_mut(r);
+    let (dest_low, dest_high) = dest.split_at_mut(l + r);
+    pool.join(
+        || par_merge(pool, left_low, right_low, dest_low, compare),
+        || par_merge(pool, left_high, right_high, dest_high, compare),
+    );
+}
+
+fn merge<T, F>(left: &[MaybeUninit<T>], right: &[MaybeUninit<T>], dest: &mut [T], compare: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let mut hole = MergeHole::new(left, right, dest);
+    unsafe {
+        while hole.left < hole.left_end && hole.right < hole.right_end {
+            // Ties are taken from the left run, which keeps the sort stable.
+            let from = if compare(&*hole.right, &*hole.left).is_lt() {
+                &mut hole.right
+            } else {
+                &mut hole.left
+            };
+            ptr::copy_nonoverlapping(*from, hole.dest, 1);
+            *from = from.add(1);
+            hole.dest = hole.dest.add(1);
+        }
+    }
+    // Dropping the hole moves what's left of the longer run.
+}
+
+/// Elements of two runs not merged yet and where they go. On drop, also when
+/// `compare` panics, they're moved to `dest` in order, so every slot of the
+/// destination ends up holding exactly one element.
+struct MergeHole<T> {
+    left: *const T,
+    left_end: *const T,
+    right: *const T,
+    right_end: *const T,
+    dest: *mut T,
+}
+
+impl<T> MergeHole<T> {
+    fn new(left: &[MaybeUninit<T>], right: &[MaybeUninit<T>], dest: &mut [T]) -> Self {
+        let left = left.as_ptr_range();
+        let right = right.as_ptr_range();
+        MergeHole {
+            left: left.start.cast(),
+            left_end: left.end.cast(),
+            right: right.start.cast(),
+            right_end: right.end.cast(),
+            dest: dest.as_mut_ptr(),
+        }
+    }
+}
+
+impl<T> Drop for MergeHole<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let left = self.left_end.offset_from(self.left) as usize;
+            ptr::copy_nonoverlapping(self.left, self.dest, left);
+            let right = self.right_end.offset_from(self.right) as usize;
+            ptr::copy_nonoverlapping(self.right, self.dest.add(left), right);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random(len: usize, modulo: u64) -> Vec<u64> {
+        let mut x = 0x2545_f491_4f6c_dd1d_u64;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x % modulo
+            })
+            .collect()
+    }
+
+    #[test]
+    fn quicksort_matches_std() {
+        let pool = Factory::new().build_threadpool(4);
+        for modulo in [u64::MAX, 100, 1] {
+            let mut v = random(100_000, modulo);
+            let mut expected = v.clone();
+            expected.sort_unstable();
+            par_sort(&pool, &mut v);
+            assert_eq!(v, expected);
+        }
+    }
+
+    #[test]
+    fn sorted_and_reversed_input() {
+        let pool = Factory::new().build_threadpool(2);
+        let mut v: Vec<u32> = (0..50_000).collect();
+        par_sort(&pool, &mut v);
+        assert!(v.windows(2).all(|w| w[0] <= w[1]));
+        v.reverse();
+        par_sort_stable(&pool, &mut v);
+        assert!(v.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn by_key_sorts_descending() {
+        let pool = Factory::new().build_threadpool(3);
+        let mut v = random(20_000, 1000);
+        par_sort_by_key(&pool, &mut v, |x| std::cmp::Reverse(*x));
+        assert!(v.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn stable_sort_keeps_equal_elements_in_order() {
+        let pool = Factory::new().build_threadpool(4);
+        let mut v: Vec<(u64, usize)> = random(60_000, 50).into_iter().zip(0..).collect();
+        par_sort_stable_by_key(&pool, &mut v, |(key, _)| *key);
+        assert!(v
+            .windows(2)
+            .all(|w| w[0].0 < w[1].0 || (w[0].0 == w[1].0 && w[0].1 < w[1].1)));
+    }
+
+    #[test]
+    fn stable_sort_keeps_every_element_when_compare_panics() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Counted(u64);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        let pool = Factory::new().build_threadpool(4);
+        let keys = random(50_000, 1000);
+        let mut v: Vec<Counted> = keys.iter().copied().map(Counted).collect();
+        // Late enough to be in one of the merges rather than the chunk sorts.
+        let calls = AtomicUsize::new(0);
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            par_sort_stable_by(&pool, &mut v, |a, b| {
+                if calls.fetch_add(1, SeqCst) == 700_000 {
+                    panic!("compare");
+                }
+                a.0.cmp(&b.0)
+            })
+        }));
+        assert!(res.is_err());
+        let mut left: Vec<u64> = v.iter().map(|c| c.0).collect();
+        left.sort_unstable();
+        let mut expected = keys;
+        expected.sort_unstable();
+        assert_eq!(left, expected);
+        drop(v);
+        assert_eq!(DROPS.load(SeqCst), 50_000);
+    }
+
+    #[test]
+    fn small_slices_and_no_threads() {
+        let pool = Factory::new();
+        let mut empty: Vec<i32> = Vec::new();
+        par_sort(&pool, &mut empty);
+        let mut v = vec![3, 1, 2];
+        par_sort_stable(&pool, &mut v);
+        assert_eq!(v, vec![1, 2, 3]);
+        let mut v = random(10_000, 7);
+        par_sort(&pool, &mut v);
+        assert!(v.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn partition_splits_around_the_pivot() {
+        let mut v = vec![5, 1, 5, 9, 3, 5, 7, 0];
+        let (lt, gt) = partition(&mut v, &i32::cmp);
+        let pivot = v[lt];
+        assert!(v[..lt].iter().all(|x| *x < pivot));
+        assert!(v[lt..gt].iter().all(|x| *x == pivot));
+        assert!(v[gt..].iter().all(|x| *x > pivot));
+    }
+}